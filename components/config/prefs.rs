@@ -161,6 +161,7 @@ mod gen {
             dom: {
                 webgpu: {
                     enabled: bool,
+                    max_buffers_per_device: i64,
                 },
                 bluetooth: {
                     enabled: bool,
@@ -2006,11 +2006,11 @@ where
                     EmbedderMsg::MediaSessionEvent(event),
                 ));
             },
-            FromScriptMsg::RequestAdapter(sender, options, ids) => self
+            FromScriptMsg::RequestAdapter(sender, options, force_fallback_adapter, ids) => self
                 .handle_request_wgpu_adapter(
                     source_pipeline_id,
                     BrowsingContextId::from(source_top_ctx_id),
-                    FromScriptMsg::RequestAdapter(sender, options, ids),
+                    FromScriptMsg::RequestAdapter(sender, options, force_fallback_adapter, ids),
                 ),
         }
     }
@@ -2205,16 +2205,22 @@ where
             .get_mut(&browsing_context_group_id)
         {
             Some(browsing_context_group) => {
-                let adapter_request =
-                    if let FromScriptMsg::RequestAdapter(sender, options, ids) = request {
-                        WebGPURequest::RequestAdapter {
-                            sender,
-                            options,
-                            ids,
-                        }
-                    } else {
-                        return warn!("Wrong message type in handle_request_wgpu_adapter");
-                    };
+                let adapter_request = if let FromScriptMsg::RequestAdapter(
+                    sender,
+                    options,
+                    force_fallback_adapter,
+                    ids,
+                ) = request
+                {
+                    WebGPURequest::RequestAdapter {
+                        sender,
+                        options,
+                        force_fallback_adapter,
+                        ids,
+                    }
+                } else {
+                    return warn!("Wrong message type in handle_request_wgpu_adapter");
+                };
                 let send = match browsing_context_group.webgpus.entry(host) {
                     Entry::Vacant(v) => v
                         .insert(match WebGPU::new() {
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use gfx::text::util::{transform_text, CompressionMode};
+use gfx::text::util::{capitalize, to_lowercase, to_uppercase, transform_text, CompressionMode};
 
 #[test]
 fn test_transform_compress_none() {
@@ -104,3 +104,38 @@ fn test_transform_compress_whitespace_newline_no_incoming() {
         assert_eq!(trimmed_str, oracle)
     }
 }
+
+#[test]
+fn test_to_uppercase() {
+    assert_eq!(to_uppercase("straße", ""), "STRASSE");
+    assert_eq!(to_uppercase("istanbul", "en"), "ISTANBUL");
+    assert_eq!(to_uppercase("istanbul", "tr"), "İSTANBUL");
+    assert_eq!(to_uppercase("bakı", "az-Latn"), "BAKI");
+}
+
+#[test]
+fn test_to_lowercase() {
+    assert_eq!(to_lowercase("ISTANBUL", ""), "istanbul");
+    assert_eq!(to_lowercase("ISPARTA İZMİR", "tr"), "ısparta izmir");
+}
+
+#[test]
+fn test_capitalize() {
+    let pairs = [
+        ("hello world", "Hello World"),
+        ("ßeta", "Sseta"),
+        (
+            "«bonjour» ¿qué? 「こんにちは」",
+            "«Bonjour» ¿Qué? 「こんにちは」",
+        ),
+        ("don't stop", "Don't Stop"),
+        ("don’t stop", "Don’t Stop"),
+        ("the 3rd time", "The 3rd Time"),
+        ("(first) second-third", "(First) Second-Third"),
+    ];
+    for &(text, oracle) in pairs.iter() {
+        assert_eq!(capitalize(text, ""), oracle);
+    }
+    assert_eq!(capitalize("istanbul izmir", "tr"), "İstanbul İzmir");
+    assert_eq!(capitalize("istanbul izmir", "en"), "Istanbul Izmir");
+}
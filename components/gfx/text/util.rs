@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use ucd::{Codepoint, UnicodeBlock};
+use ucd::{Codepoint, UnicodeBlock, UnicodeCategory};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum CompressionMode {
@@ -104,6 +104,112 @@ pub fn transform_text(
     }
 }
 
+/// Whether `lang` is Turkish or Azeri, whose case mappings have a dotted and a
+/// dotless i.
+fn is_turkic(lang: &str) -> bool {
+    let primary = lang.split(|c| c == '-' || c == '_').next().unwrap_or("");
+    primary.eq_ignore_ascii_case("tr") || primary.eq_ignore_ascii_case("az")
+}
+
+/// `text-transform: uppercase` for text in the language `lang`.
+pub fn to_uppercase(text: &str, lang: &str) -> String {
+    if is_turkic(lang) {
+        text.replace('i', "\u{130}").to_uppercase()
+    } else {
+        text.to_uppercase()
+    }
+}
+
+/// `text-transform: lowercase` for text in the language `lang`.
+pub fn to_lowercase(text: &str, lang: &str) -> String {
+    if is_turkic(lang) {
+        text.replace('\u{130}', "i")
+            .replace('I', "\u{131}")
+            .to_lowercase()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+/// `text-transform: capitalize` for text in the language `lang`: the first letter
+/// of each word is put in titlecase, the other letters are left alone.
+pub fn capitalize(text: &str, lang: &str) -> String {
+    let turkic = is_turkic(lang);
+    let mut capitalized = String::with_capacity(text.len());
+    let mut capitalize_next_letter = true;
+    for character in text.chars() {
+        // FIXME: This should be a typographic letter unit, not an alphabetic character:
+        // https://drafts.csswg.org/css-text/#typographic-letter-unit
+        if capitalize_next_letter && character.is_alphabetic() {
+            match character {
+                'i' if turkic => capitalized.push('\u{130}'),
+                _ => push_titlecase(character, &mut capitalized),
+            }
+            capitalize_next_letter = false;
+            continue;
+        }
+        capitalized.push(character);
+        match character {
+            // An apostrophe neither starts nor ends a word, as in "don't".
+            '\'' | '\u{2019}' => {},
+            // Only whitespace and punctuation start a new word, digits don't, as in "3rd".
+            c if c.is_whitespace() || is_punctuation(c) => capitalize_next_letter = true,
+            _ => capitalize_next_letter = false,
+        }
+    }
+    capitalized
+}
+
+/// Appends the titlecase mapping of `character`. It is its uppercase mapping,
+/// except for the ligatures and digraphs below, from SpecialCasing.txt and
+/// UnicodeData.txt, and for Georgian letters, which have no titlecase.
+///
+/// FIXME: Greek letters with an iota subscript are put in uppercase, not titlecase.
+fn push_titlecase(character: char, output: &mut String) {
+    let titlecase = match character {
+        '\u{DF}' => "Ss",
+        '\u{1C4}' | '\u{1C5}' | '\u{1C6}' => "\u{1C5}",
+        '\u{1C7}' | '\u{1C8}' | '\u{1C9}' => "\u{1C8}",
+        '\u{1CA}' | '\u{1CB}' | '\u{1CC}' => "\u{1CB}",
+        '\u{1F1}' | '\u{1F2}' | '\u{1F3}' => "\u{1F2}",
+        '\u{587}' => "\u{535}\u{582}",
+        '\u{FB00}' => "Ff",
+        '\u{FB01}' => "Fi",
+        '\u{FB02}' => "Fl",
+        '\u{FB03}' => "Ffi",
+        '\u{FB04}' => "Ffl",
+        '\u{FB05}' | '\u{FB06}' => "St",
+        '\u{FB13}' => "\u{544}\u{576}",
+        '\u{FB14}' => "\u{544}\u{565}",
+        '\u{FB15}' => "\u{544}\u{56B}",
+        '\u{FB16}' => "\u{54E}\u{576}",
+        '\u{FB17}' => "\u{544}\u{56D}",
+        '\u{10D0}'..='\u{10FA}' | '\u{10FD}'..='\u{10FF}' => {
+            output.push(character);
+            return;
+        },
+        _ => {
+            output.extend(character.to_uppercase());
+            return;
+        },
+    };
+    output.push_str(titlecase);
+}
+
+/// Whether `c` is in one of the punctuation general categories (P*).
+fn is_punctuation(c: char) -> bool {
+    match c.category() {
+        UnicodeCategory::ConnectorPunctuation |
+        UnicodeCategory::DashPunctuation |
+        UnicodeCategory::OpenPunctuation |
+        UnicodeCategory::ClosePunctuation |
+        UnicodeCategory::InitialPunctuation |
+        UnicodeCategory::FinalPunctuation |
+        UnicodeCategory::OtherPunctuation => true,
+        _ => false,
+    }
+}
+
 pub fn float_to_fixed(before: usize, f: f64) -> i32 {
     ((1i32 << before) as f64 * f) as i32
 }
@@ -269,11 +269,7 @@ impl<'a> BuilderForBoxFragment<'a> {
                 ImageLayer::None => {},
                 ImageLayer::Image(image) => match image {
                     Image::Gradient(gradient) => {
-                        let intrinsic = IntrinsicSizes {
-                            width: None,
-                            height: None,
-                            ratio: None,
-                        };
+                        let intrinsic = IntrinsicSizes::empty();
                         if let Some(layer) =
                             &background::layout_layer(self, builder, index, intrinsic)
                         {
@@ -19,7 +19,7 @@ use script_layout_interface::HTMLCanvasDataSource;
 use servo_arc::Arc as ServoArc;
 use std::marker::PhantomData as marker;
 use std::sync::{Arc, Mutex};
-use style::dom::{OpaqueNode, TNode};
+use style::dom::{OpaqueNode, TElement, TNode};
 use style::properties::ComputedValues;
 use style::selector_parser::PseudoElement;
 use style::values::generics::counters::Content;
@@ -355,11 +355,14 @@ pub(crate) trait NodeExt<'dom>: 'dom + Copy + LayoutNode + Send + Sync {
 
     /// Returns the image if it’s loaded, and its size in image pixels
     /// adjusted for `image_density`.
-    fn as_image(self) -> Option<(Option<Arc<NetImage>>, PhysicalSize<f64>)>;
+    fn as_image(self) -> Option<(Option<Arc<NetImage>>, Option<PhysicalSize<f64>>)>;
     fn as_canvas(self) -> Option<(CanvasInfo, PhysicalSize<f64>)>;
     fn first_child(self) -> Option<Self>;
     fn next_sibling(self) -> Option<Self>;
     fn parent_node(self) -> Option<Self>;
+    /// The language of the content of this node, from the nearest `lang` attribute,
+    /// or empty if there is none.
+    fn language(self) -> String;
     fn style(self, context: &LayoutContext) -> ServoArc<ComputedValues>;
 
     fn as_opaque(self) -> OpaqueNode;
@@ -386,20 +389,31 @@ where
         }
     }
 
-    fn as_image(self) -> Option<(Option<Arc<NetImage>>, PhysicalSize<f64>)> {
+    fn as_image(self) -> Option<(Option<Arc<NetImage>>, Option<PhysicalSize<f64>>)> {
         let node = self.to_threadsafe();
         let (resource, metadata) = node.image_data()?;
-        let (width, height) = resource
+        // Broken images have no intrinsic dimensions. Images whose metadata has not
+        // been loaded yet are 0x0 until it is, rather than taking up the default size.
+        let size = resource
             .as_ref()
             .map(|image| (image.width, image.height))
             .or_else(|| metadata.map(|metadata| (metadata.width, metadata.height)))
-            .unwrap_or((0, 0));
-        let (mut width, mut height) = (width as f64, height as f64);
-        if let Some(density) = node.image_density().filter(|density| *density != 1.) {
-            width = width / density;
-            height = height / density;
-        }
-        Some((resource, PhysicalSize::new(width, height)))
+            .or_else(|| {
+                if node.image_is_broken() {
+                    None
+                } else {
+                    Some((0, 0))
+                }
+            })
+            .map(|(width, height)| {
+                let (mut width, mut height) = (width as f64, height as f64);
+                if let Some(density) = node.image_density().filter(|density| *density != 1.) {
+                    width = width / density;
+                    height = height / density;
+                }
+                PhysicalSize::new(width, height)
+            });
+        Some((resource, size))
     }
 
     fn as_canvas(self) -> Option<(CanvasInfo, PhysicalSize<f64>)> {
@@ -432,6 +446,17 @@ where
         TNode::parent_node(&self)
     }
 
+    fn language(self) -> String {
+        let mut node = Some(self);
+        while let Some(current) = node {
+            if let Some(lang) = current.as_element().and_then(|element| element.lang_attr()) {
+                return lang;
+            }
+            node = NodeExt::parent_node(current);
+        }
+        String::new()
+    }
+
     fn style(self, context: &LayoutContext) -> ServoArc<ComputedValues> {
         self.to_threadsafe().style(context.shared_context())
     }
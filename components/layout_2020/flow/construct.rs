@@ -19,6 +19,9 @@ use servo_arc::Arc;
 use std::convert::{TryFrom, TryInto};
 use style::properties::ComputedValues;
 use style::selector_parser::PseudoElement;
+use style::values::computed::Length;
+use style::values::specified::text::TextTransformCase;
+use style::Zero;
 
 impl BlockFormattingContext {
     pub fn construct<'dom>(
@@ -28,10 +31,10 @@ impl BlockFormattingContext {
         contents: NonReplacedContents,
         content_sizes: ContentSizesRequest,
     ) -> (Self, BoxContentSizes) {
+        // Floats contribute to the `inline_content_sizes` of the block container they are in,
+        // see `OuterContentSizesOfChildren`.
         let (contents, contains_floats, inline_content_sizes) =
             BlockContainer::construct(context, node, style, contents, content_sizes);
-        // FIXME: add contribution to `inline_content_sizes` of floats in this formatting context
-        // https://dbaron.org/css/intrinsic/#intrinsic
         let bfc = Self {
             contents,
             contains_floats: contains_floats == ContainsFloats::Yes,
@@ -177,11 +180,11 @@ impl BlockContainer {
 
         struct Accumulator {
             contains_floats: ContainsFloats,
-            outer_content_sizes_of_children: ContentSizes,
+            outer_content_sizes_of_children: OuterContentSizesOfChildren,
         }
         let mut acc = Accumulator {
             contains_floats: builder.contains_floats,
-            outer_content_sizes_of_children: ContentSizes::zero(),
+            outer_content_sizes_of_children: OuterContentSizesOfChildren::zero(),
         };
         let mapfold = |acc: &mut Accumulator, creator: BlockLevelJob<'dom, _>| {
             let (block_level_box, box_contains_floats) = creator.finish(
@@ -191,7 +194,11 @@ impl BlockContainer {
             acc.contains_floats |= box_contains_floats;
             block_level_box
         };
-        let block_level_boxes = if context.use_rayon {
+        // The contribution of a float depends on the in-flow boxes that follow it,
+        // which `mapfold_reduce_into` does not preserve.
+        let parallel = context.use_rayon &&
+            !(content_sizes.requests_inline() && builder.contains_floats == ContainsFloats::Yes);
+        let block_level_boxes = if parallel {
             builder
                 .block_level_boxes
                 .into_par_iter()
@@ -200,7 +207,7 @@ impl BlockContainer {
                     mapfold,
                     || Accumulator {
                         contains_floats: ContainsFloats::No,
-                        outer_content_sizes_of_children: ContentSizes::zero(),
+                        outer_content_sizes_of_children: OuterContentSizesOfChildren::zero(),
                     },
                     |left, right| {
                         left.contains_floats |= right.contains_floats;
@@ -224,7 +231,7 @@ impl BlockContainer {
             contains_floats,
             outer_content_sizes_of_children,
         } = acc;
-        let content_sizes = content_sizes.compute(|| outer_content_sizes_of_children);
+        let content_sizes = content_sizes.compute(|| outer_content_sizes_of_children.finish());
         (container, contains_floats, content_sizes)
     }
 }
@@ -324,10 +331,15 @@ where
 
             if let Some(text) = new_text_run_contents {
                 let parent_style = parent_style.clone();
+                let lang = match parent_style.get_inherited_text().text_transform.case_ {
+                    TextTransformCase::None => String::new(),
+                    _ => node.language(),
+                };
                 inlines.push(ArcRefCell::new(InlineLevelBox::TextRun(TextRun {
                     tag: node.as_opaque(),
                     parent_style,
                     text,
+                    lang,
                 })))
             }
         }
@@ -652,7 +664,7 @@ where
     fn finish(
         self,
         context: &LayoutContext,
-        max_assign_in_flow_outer_content_sizes_to: Option<&mut ContentSizes>,
+        add_outer_content_sizes_to: Option<&mut OuterContentSizesOfChildren>,
     ) -> (ArcRefCell<BlockLevelBox>, ContainsFloats) {
         let node = self.node;
         let style = self.style;
@@ -663,12 +675,11 @@ where
                     node,
                     &style,
                     ContentSizesRequest::inline_if(
-                        max_assign_in_flow_outer_content_sizes_to.is_some() &&
-                            !style.inline_size_is_length(),
+                        add_outer_content_sizes_to.is_some() && !style.inline_size_is_length(),
                     ),
                 );
-                if let Some(to) = max_assign_in_flow_outer_content_sizes_to {
-                    to.max_assign(&box_content_sizes.outer_inline(&style))
+                if let Some(to) = add_outer_content_sizes_to {
+                    to.add_in_flow(box_content_sizes.outer_inline(&style))
                 }
                 let block_level_box = ArcRefCell::new(BlockLevelBox::SameFormattingContextBlock {
                     tag: node.as_opaque(),
@@ -682,8 +693,7 @@ where
                 contents,
             } => {
                 let content_sizes = ContentSizesRequest::inline_if(
-                    max_assign_in_flow_outer_content_sizes_to.is_some() &&
-                        !style.inline_size_is_length(),
+                    add_outer_content_sizes_to.is_some() && !style.inline_size_is_length(),
                 );
                 let contents = IndependentFormattingContext::construct(
                     context,
@@ -693,8 +703,8 @@ where
                     contents,
                     content_sizes,
                 );
-                if let Some(to) = max_assign_in_flow_outer_content_sizes_to {
-                    to.max_assign(&contents.content_sizes.outer_inline(&contents.style))
+                if let Some(to) = add_outer_content_sizes_to {
+                    to.add_in_flow(contents.content_sizes.outer_inline(&contents.style))
                 }
                 (
                     ArcRefCell::new(BlockLevelBox::Independent(contents)),
@@ -721,9 +731,16 @@ where
                 display_inside,
                 contents,
             } => {
-                let block_level_box = ArcRefCell::new(BlockLevelBox::OutOfFlowFloatBox(
-                    FloatBox::construct(context, node, style, display_inside, contents),
-                ));
+                let float_box = FloatBox::construct(context, node, style, display_inside, contents);
+                if let Some(to) = add_outer_content_sizes_to {
+                    to.add_float(
+                        float_box
+                            .contents
+                            .content_sizes
+                            .outer_inline(&float_box.contents.style),
+                    )
+                }
+                let block_level_box = ArcRefCell::new(BlockLevelBox::OutOfFlowFloatBox(float_box));
                 (block_level_box, ContainsFloats::Yes)
             },
         };
@@ -733,6 +750,50 @@ where
     }
 }
 
+/// Accumulates the outer content sizes of the block-level boxes of a block container,
+/// for the min/max-content of the container.
+///
+/// https://dbaron.org/css/intrinsic/#intrinsic
+struct OuterContentSizesOfChildren {
+    /// Contributions of in-flow boxes, each including the floats just before it
+    max: ContentSizes,
+    /// Max-content of the floats since the last in-flow box
+    pending_floats: Length,
+}
+
+impl OuterContentSizesOfChildren {
+    fn zero() -> Self {
+        Self {
+            max: ContentSizes::zero(),
+            pending_floats: Length::zero(),
+        }
+    }
+
+    fn add_in_flow(&mut self, mut outer: ContentSizes) {
+        // With enough room, an in-flow box flows beside the floats that precede it.
+        outer.max_content += std::mem::replace(&mut self.pending_floats, Length::zero());
+        self.max.max_assign(&outer)
+    }
+
+    fn add_float(&mut self, outer: ContentSizes) {
+        // Consecutive floats line up side by side, but each can wrap on its own line.
+        self.max.min_content.max_assign(outer.min_content);
+        self.pending_floats += outer.max_content;
+    }
+
+    /// Combines the contributions of consecutive sequences of boxes that contain no float.
+    fn max_assign(&mut self, other: &Self) {
+        debug_assert!(self.pending_floats == Length::zero());
+        debug_assert!(other.pending_floats == Length::zero());
+        self.max.max_assign(&other.max)
+    }
+
+    fn finish(mut self) -> ContentSizes {
+        self.max.max_content.max_assign(self.pending_floats);
+        self.max
+    }
+}
+
 impl IntermediateBlockContainer {
     fn finish<'dom>(
         self,
@@ -21,11 +21,13 @@ use crate::style_ext::{ComputedValuesExt, Display, DisplayGeneratingBox, Display
 use crate::ContainingBlock;
 use app_units::Au;
 use gfx::text::text_run::GlyphRun;
+use gfx::text::util;
 use servo_arc::Arc;
+use std::borrow::Cow;
 use style::dom::OpaqueNode;
 use style::properties::ComputedValues;
 use style::values::computed::{Length, LengthPercentage, Percentage};
-use style::values::specified::text::TextAlignKeyword;
+use style::values::specified::text::{TextAlignKeyword, TextTransform, TextTransformCase};
 use style::Zero;
 use webrender_api::FontInstanceKey;
 
@@ -60,6 +62,9 @@ pub(crate) struct TextRun {
     #[serde(skip_serializing)]
     pub parent_style: Arc<ComputedValues>,
     pub text: String,
+    /// The content language, for case mapping. Only looked up when `text-transform`
+    /// maps case, empty otherwise.
+    pub lang: String,
 }
 
 struct InlineNestingLevelState<'box_tree> {
@@ -128,9 +133,17 @@ impl InlineFormattingContext {
     pub(super) fn inline_content_sizes(&self, layout_context: &LayoutContext) -> ContentSizes {
         struct Computation {
             paragraph: ContentSizes,
-            current_line: ContentSizes,
+            current_line: LineContentSizes,
             current_line_percentages: Percentage,
         }
+        /// The sizes of the current line are sums of many (possibly tiny) advances,
+        /// so they are accumulated in `Au` and only converted back to `Length`
+        /// when folded into `paragraph`. Otherwise very long lines drift.
+        #[derive(Default)]
+        struct LineContentSizes {
+            min_content: Au,
+            max_content: Au,
+        }
         impl Computation {
             fn traverse(
                 &mut self,
@@ -169,7 +182,7 @@ impl InlineFormattingContext {
                                 self.line_break_opportunity()
                             }
                             for run in &runs {
-                                let advance = Length::from(run.glyph_store.total_advance());
+                                let advance = run.glyph_store.total_advance();
                                 if run.glyph_store.is_whitespace() {
                                     self.line_break_opportunity()
                                 } else {
@@ -182,11 +195,19 @@ impl InlineFormattingContext {
                             let (outer, pc) = atomic
                                 .content_sizes
                                 .outer_inline_and_percentages(&atomic.style);
-                            self.current_line.min_content += outer.min_content;
-                            self.current_line.max_content += outer.max_content;
+                            self.current_line.min_content += Au::from(outer.min_content);
+                            self.current_line.max_content += Au::from(outer.max_content);
                             self.current_line_percentages += pc;
                         },
-                        InlineLevelBox::OutOfFlowFloatBox(_) |
+                        InlineLevelBox::OutOfFlowFloatBox(float_box) => {
+                            // With enough room, a float sits beside the line it is on.
+                            let outer = float_box
+                                .contents
+                                .content_sizes
+                                .outer_inline(&float_box.contents.style);
+                            self.paragraph.min_content.max_assign(outer.min_content);
+                            self.current_line.max_content += Au::from(outer.max_content);
+                        },
                         InlineLevelBox::OutOfFlowAbsolutelyPositionedBox(_) => {},
                     }
                 }
@@ -202,23 +223,28 @@ impl InlineFormattingContext {
             }
 
             fn add_length(&mut self, l: Length) {
-                self.current_line.min_content += l;
-                self.current_line.max_content += l;
+                self.current_line.min_content += Au::from(l);
+                self.current_line.max_content += Au::from(l);
             }
 
             fn line_break_opportunity(&mut self) {
+                let min_content = std::mem::replace(&mut self.current_line.min_content, Au(0));
                 self.paragraph
                     .min_content
-                    .max_assign(take(&mut self.current_line.min_content));
+                    .max_assign(Length::from(min_content));
             }
 
             fn forced_line_break(&mut self) {
                 self.line_break_opportunity();
-                self.current_line
-                    .adjust_for_pbm_percentages(take(&mut self.current_line_percentages));
+                let max_content = std::mem::replace(&mut self.current_line.max_content, Au(0));
+                let mut current_line = ContentSizes {
+                    min_content: Length::zero(),
+                    max_content: Length::from(max_content),
+                };
+                current_line.adjust_for_pbm_percentages(take(&mut self.current_line_percentages));
                 self.paragraph
                     .max_content
-                    .max_assign(take(&mut self.current_line.max_content));
+                    .max_assign(current_line.max_content);
             }
         }
         fn take<T: Zero>(x: &mut T) -> T {
@@ -226,7 +252,7 @@ impl InlineFormattingContext {
         }
         let mut computation = Computation {
             paragraph: ContentSizes::zero(),
-            current_line: ContentSizes::zero(),
+            current_line: LineContentSizes::default(),
             current_line_percentages: Percentage::zero(),
         };
         computation.traverse(layout_context, &self.inline_level_boxes);
@@ -691,9 +717,14 @@ impl TextRun {
                 flags,
             };
 
+            // Transform before shaping, so that both the intrinsic sizes and the laid out
+            // fragments are based on the rendered text. Case mapping can change the
+            // number of characters, for example `ß` becomes `SS`.
+            let text =
+                apply_text_transform(&self.text, inherited_text_style.text_transform, &self.lang);
             let (runs, break_at_start) = gfx::text::text_run::TextRun::break_and_shape(
                 &mut font,
-                &self.text,
+                &text,
                 &shaping_options,
                 &mut None,
             );
@@ -857,3 +888,20 @@ impl<'box_tree> Iterator for InlineBoxChildIter<'box_tree> {
         }
     }
 }
+
+/// https://drafts.csswg.org/css-text/#text-transform-property
+///
+/// `capitalize` only looks at this text run, so a word split across inline boxes
+/// is capitalized again after the split. `full-width` and `full-size-kana` are ignored.
+fn apply_text_transform<'text>(
+    text: &'text str,
+    text_transform: TextTransform,
+    lang: &str,
+) -> Cow<'text, str> {
+    match text_transform.case_ {
+        TextTransformCase::None => Cow::Borrowed(text),
+        TextTransformCase::Uppercase => Cow::Owned(util::to_uppercase(text, lang)),
+        TextTransformCase::Lowercase => Cow::Owned(util::to_lowercase(text, lang)),
+        TextTransformCase::Capitalize => Cow::Owned(util::capitalize(text, lang)),
+    }
+}
@@ -17,9 +17,11 @@ use net_traits::image_cache::{ImageOrMetadataAvailable, UsePlaceholder};
 use servo_arc::Arc as ServoArc;
 use std::fmt;
 use std::sync::{Arc, Mutex};
+use style::logical_geometry::WritingMode;
 use style::properties::ComputedValues;
 use style::servo::url::ComputedUrl;
 use style::values::computed::{Length, LengthOrAuto};
+use style::values::generics::length::MaxSize;
 use style::values::CSSFloat;
 use style::Zero;
 use webrender_api::ImageKey;
@@ -48,6 +50,25 @@ pub(crate) struct IntrinsicSizes {
     pub ratio: Option<CSSFloat>,
 }
 
+impl IntrinsicSizes {
+    pub(crate) fn empty() -> Self {
+        Self {
+            width: None,
+            height: None,
+            ratio: None,
+        }
+    }
+
+    fn from_width_and_height(width: CSSFloat, height: CSSFloat) -> Self {
+        Self {
+            width: Some(Length::new(width)),
+            height: Some(Length::new(height)),
+            // FIXME https://github.com/w3c/csswg-drafts/issues/4572
+            ratio: Some(width / height),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub(crate) enum CanvasSource {
     WebGL(ImageKey),
@@ -87,7 +108,7 @@ impl ReplacedContent {
             } else if let Some((canvas_info, intrinsic_size_in_dots)) = element.as_canvas() {
                 (
                     ReplacedContentKind::Canvas(canvas_info),
-                    intrinsic_size_in_dots,
+                    Some(intrinsic_size_in_dots),
                 )
             } else {
                 return None;
@@ -100,17 +121,14 @@ impl ReplacedContent {
         // https://drafts.csswg.org/css-images-4/#the-image-resolution
         let dppx = 1.0;
 
-        let width = (intrinsic_size_in_dots.width as CSSFloat) / dppx;
-        let height = (intrinsic_size_in_dots.height as CSSFloat) / dppx;
-        return Some(Self {
-            kind,
-            intrinsic: IntrinsicSizes {
-                width: Some(Length::new(width)),
-                height: Some(Length::new(height)),
-                // FIXME https://github.com/w3c/csswg-drafts/issues/4572
-                ratio: Some(width / height),
-            },
-        });
+        let intrinsic = match intrinsic_size_in_dots {
+            Some(size) => IntrinsicSizes::from_width_and_height(
+                (size.width as CSSFloat) / dppx,
+                (size.height as CSSFloat) / dppx,
+            ),
+            None => IntrinsicSizes::empty(),
+        };
+        return Some(Self { kind, intrinsic });
     }
 
     pub fn from_image_url<'dom>(
@@ -135,12 +153,7 @@ impl ReplacedContent {
 
             return Some(Self {
                 kind: ReplacedContentKind::Image(image),
-                intrinsic: IntrinsicSizes {
-                    width: Some(Length::new(width)),
-                    height: Some(Length::new(height)),
-                    // FIXME https://github.com/w3c/csswg-drafts/issues/4572
-                    ratio: Some(width / height),
-                },
+                intrinsic: IntrinsicSizes::from_width_and_height(width, height),
             });
         }
         None
@@ -168,10 +181,32 @@ impl ReplacedContent {
         // FIXME: min/max-content of replaced elements is not defined in
         // https://dbaron.org/css/intrinsic/
         // This seems sensible?
-        let inline = self
-            .flow_relative_intrinsic_size(style)
-            .inline
-            .unwrap_or(Length::zero());
+        let intrinsic_size = self.flow_relative_intrinsic_size(style);
+        let intrinsic_ratio = self.inline_size_over_block_size_intrinsic_ratio(style);
+        // As in `used_size_as_if_inline_element`, a specified block size goes through the ratio,
+        // once clamped by 'min-height' and 'max-height'. Percentages can't be resolved here,
+        // they are treated as initial values, except in 'min-height' where they resolve
+        // against zero like in `ContentSizes::outer_inline_and_percentages`.
+        let min_block_size = style
+            .min_box_size()
+            .block
+            .percentage_relative_to(Length::zero())
+            .auto_is(Length::zero);
+        let max_block_size = match style.max_box_size().block {
+            MaxSize::None => None,
+            MaxSize::LengthPercentage(ref lp) => lp.to_length(),
+        };
+        let block_size = style
+            .box_size()
+            .block
+            .non_auto()
+            .and_then(|lp| lp.to_length())
+            .map(|block| block.clamp_between_extremums(min_block_size, max_block_size));
+        let inline = match (block_size, intrinsic_size.inline, intrinsic_ratio) {
+            (Some(block), _, Some(i_over_b)) => block * i_over_b,
+            (_, Some(inline), _) => inline,
+            _ => default_object_size(style.writing_mode).inline,
+        };
         ContentSizes {
             min_content: inline,
             max_content: inline,
@@ -254,17 +289,7 @@ impl ReplacedContent {
             .max_box_size()
             .percentages_relative_to(containing_block);
 
-        let default_object_size = || {
-            // FIXME:
-            // “If 300px is too wide to fit the device, UAs should use the width of
-            //  the largest rectangle that has a 2:1 ratio and fits the device instead.”
-            // “height of the largest rectangle that has a 2:1 ratio, has a height not greater
-            //  than 150px, and has a width not greater than the device width.”
-            Vec2::from_physical_size(
-                &PhysicalSize::new(Length::new(300.), Length::new(150.)),
-                mode,
-            )
-        };
+        let default_object_size = || default_object_size(mode);
         let clamp = |inline_size: Length, block_size: Length| Vec2 {
             inline: inline_size.clamp_between_extremums(min_box_size.inline, max_box_size.inline),
             block: block_size.clamp_between_extremums(min_box_size.block, max_box_size.block),
@@ -313,7 +338,7 @@ impl ReplacedContent {
                         // In this `match` expression this would be an additional arm here:
                         //
                         // ```
-                        // (Vec2 { inline: None, block: None }, Some(_)) => {…}
+                        // (None, None, Some(_)) => {…}
                         // ```
                         //
                         // “[…] then the used value of 'width' is undefined in CSS 2.
@@ -321,6 +346,9 @@ impl ReplacedContent {
                         // does not itself depend on the replaced element's width,
                         // then the used value of 'width' is calculated from the constraint
                         // equation used for block-level, non-replaced elements in normal flow.”
+                        //
+                        // `IntrinsicSizes` only has a ratio along with both dimensions for now,
+                        // so this case cannot happen yet.
                         _ => default_object_size().inline,
                     };
                 let block_size = if let Some(block) = intrinsic_size.block {
@@ -440,3 +468,19 @@ impl ReplacedContent {
         }
     }
 }
+
+/// https://drafts.csswg.org/css-images/#default-object-size
+///
+/// Used for replaced elements that lack the intrinsic dimensions needed to size them,
+/// such as an `<img>` whose source failed to load.
+fn default_object_size(mode: WritingMode) -> Vec2<Length> {
+    // FIXME:
+    // “If 300px is too wide to fit the device, UAs should use the width of
+    //  the largest rectangle that has a 2:1 ratio and fits the device instead.”
+    // “height of the largest rectangle that has a 2:1 ratio, has a height not greater
+    //  than 150px, and has a width not greater than the device width.”
+    Vec2::from_physical_size(
+        &PhysicalSize::new(Length::new(300.), Length::new(150.)),
+        mode,
+    )
+}
@@ -5,6 +5,7 @@
 //! https://drafts.csswg.org/css-sizing/
 
 use crate::style_ext::ComputedValuesExt;
+use app_units::Au;
 use style::properties::ComputedValues;
 use style::values::computed::{Length, LengthPercentage, Percentage};
 use style::values::generics::length::MaxSize;
@@ -68,17 +69,45 @@ impl ContentSizes {
         self.max_content.max_assign(other.max_content);
     }
 
+    /// Clamps both sizes to 'min-*' and 'max-*' values.
+    /// When the minimum is larger than the maximum, the minimum wins.
+    /// https://drafts.csswg.org/css2/visudet.html#min-max-widths
+    pub fn clamp_between_extremums(&self, min: Length, max: Option<Length>) -> Self {
+        Self {
+            min_content: self.min_content.clamp_between_extremums(min, max),
+            max_content: self.max_content.clamp_between_extremums(min, max),
+        }
+    }
+
+    /// `min(max(min-content, available), max-content)`, in whichever axis these sizes are.
+    /// https://drafts.csswg.org/css2/visudet.html#shrink-to-fit-float
+    pub fn shrink_to_fit(&self, available_size: Length) -> Length {
+        available_size.max(self.min_content).min(self.max_content)
+    }
+
     /// Relevant to outer intrinsic inline sizes, for percentages from padding and margin.
     pub fn adjust_for_pbm_percentages(&mut self, percentages: Percentage) {
         // " Note that this may yield an infinite result, but undefined results
         //   (zero divided by zero) must be treated as zero. "
-        if self.max_content.px() == 0. {
-            // Avoid a potential `NaN`.
-            // Zero is already the result we want regardless of `denominator`.
-        } else {
-            let denominator = (1. - percentages.0).max(0.);
-            self.max_content = Length::new(self.max_content.px() / denominator);
+        //
+        // Like Gecko, infinite results are clamped to the largest length app units can
+        // represent, so that they don't turn into `NaN` in later arithmetic.
+        // Negative sums, from negative margins, are ignored.
+        if percentages.0 <= 0. {
+            return;
         }
+        let denominator = (1. - percentages.0).max(0.);
+        let largest = Length::new(Au::max_value().to_f32_px());
+        let adjust = |size: &mut Length| {
+            if size.px() == 0. {
+                // Avoid a potential `NaN`.
+                // Zero is already the result we want regardless of `denominator`.
+            } else {
+                *size = Length::new(size.px() / denominator).min(largest);
+            }
+        };
+        adjust(&mut self.min_content);
+        adjust(&mut self.max_content);
     }
 }
 
@@ -90,10 +119,15 @@ pub(crate) enum BoxContentSizes {
 }
 
 impl BoxContentSizes {
-    fn expect_inline(&self) -> &ContentSizes {
+    /// Not requesting the content sizes of a box that needs them is a bug,
+    /// but not one worth crashing release builds over.
+    fn expect_inline(&self) -> ContentSizes {
         match self {
-            Self::NoneWereRequested => panic!("Accessing content size that was not requested"),
-            Self::Inline(s) => s,
+            Self::NoneWereRequested => {
+                debug_assert!(false, "Accessing content size that was not requested");
+                ContentSizes::zero()
+            },
+            Self::Inline(s) => s.clone(),
         }
     }
 
@@ -115,31 +149,25 @@ impl BoxContentSizes {
             .inline
             .percentage_relative_to(Length::zero())
             .auto_is(Length::zero);
+        // Like for 'width' below, a 'max-width' containing a percentage, including in `calc()`,
+        // is treated as its initial value 'none'. Only a 'min-width' resolves it against zero.
+        // https://drafts.csswg.org/css-sizing-3/#cyclic-percentage-contribution
         let max_inline_size = match style.max_box_size().inline {
             MaxSize::None => None,
             MaxSize::LengthPercentage(ref lp) => lp.to_length(),
         };
-        let clamp = |l: Length| l.clamp_between_extremums(min_inline_size, max_inline_size);
 
         // Percentages for 'width' are treated as 'auto'
         let inline_size = inline_size.map(|lp| lp.to_length());
         // The (inner) min/max-content are only used for 'auto'
         let mut outer = match inline_size.non_auto().flatten() {
-            None => {
-                let inner = self.expect_inline().clone();
-                ContentSizes {
-                    min_content: clamp(inner.min_content),
-                    max_content: clamp(inner.max_content),
-                }
+            None => self.expect_inline(),
+            Some(length) => ContentSizes {
+                min_content: length,
+                max_content: length,
             },
-            Some(length) => {
-                let length = clamp(length);
-                ContentSizes {
-                    min_content: length,
-                    max_content: length,
-                }
-            },
-        };
+        }
+        .clamp_between_extremums(min_inline_size, max_inline_size);
 
         let mut pbm_lengths = Length::zero();
         let mut pbm_percentages = Percentage::zero();
@@ -168,9 +196,6 @@ impl BoxContentSizes {
 
     /// https://drafts.csswg.org/css2/visudet.html#shrink-to-fit-float
     pub(crate) fn shrink_to_fit(&self, available_size: Length) -> Length {
-        let inline = self.expect_inline();
-        available_size
-            .max(inline.min_content)
-            .min(inline.max_content)
+        self.expect_inline().shrink_to_fit(available_size)
     }
 }
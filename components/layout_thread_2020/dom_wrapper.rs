@@ -1211,6 +1211,11 @@ impl<'ln> ThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
         this.image_density()
     }
 
+    fn image_is_broken(&self) -> bool {
+        let this = unsafe { self.get_jsmanaged() };
+        this.image_is_broken()
+    }
+
     fn image_data(&self) -> Option<(Option<StdArc<Image>>, Option<ImageMetadata>)> {
         let this = unsafe { self.get_jsmanaged() };
         this.image_data()
@@ -11,17 +11,17 @@ use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::gpuadapter::GPUAdapter;
+use crate::dom::gpudevicelostinfo::GPUDeviceLostInfo;
 use crate::dom::promise::Promise;
 use crate::realms::InRealm;
 use crate::task_source::{TaskSource, TaskSourceName};
 use dom_struct::dom_struct;
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
-use js::jsapi::Heap;
 use script_traits::ScriptMsg;
 use std::rc::Rc;
 use webgpu::wgpu;
-use webgpu::{WebGPUResponse, WebGPUResponseResult};
+use webgpu::{WebGPUError, WebGPUMsg, WebGPUResponse, WebGPUResponseResult};
 
 #[dom_struct]
 pub struct GPU {
@@ -42,6 +42,8 @@ impl GPU {
 
 pub trait AsyncWGPUListener {
     fn handle_response(&self, response: WebGPUResponse, promise: &Rc<Promise>);
+    /// Called before `promise` is rejected with an error of the WebGPU thread.
+    fn handle_error(&self, _promise: &Rc<Promise>) {}
 }
 
 struct WGPUResponse<T: AsyncWGPUListener + DomObject> {
@@ -55,10 +57,13 @@ impl<T: AsyncWGPUListener + DomObject> WGPUResponse<T> {
         let promise = self.trusted.root();
         match response {
             Ok(response) => self.receiver.root().handle_response(response, &promise),
-            Err(error) => promise.reject_error(Error::Type(format!(
-                "Received error from WebGPU thread: {}",
-                error
-            ))),
+            Err(error) => {
+                self.receiver.root().handle_error(&promise);
+                promise.reject_error(Error::Type(format!(
+                    "Received error from WebGPU thread: {}",
+                    error
+                )));
+            },
         }
     }
 }
@@ -102,6 +107,51 @@ pub fn response_async<T: AsyncWGPUListener + DomObject + 'static>(
     action_sender
 }
 
+/// Routes the `WebGPUMsg::DeviceLost` of a device to a task resolving `promise`,
+/// its `GPUDevice.lost` promise. Its uncaptured errors are logged, since there is
+/// no `uncapturederror` event yet.
+pub fn device_lost_async(promise: &Rc<Promise>, global: &GlobalScope) -> IpcSender<WebGPUMsg> {
+    let (lost_sender, lost_receiver) = ipc::channel().unwrap();
+    let task_source = global.dom_manipulation_task_source();
+    let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+    let mut trusted = Some(TrustedPromise::new(promise.clone()));
+    ROUTER.add_route(
+        lost_receiver.to_opaque(),
+        Box::new(move |message| {
+            let reason = match message.to().unwrap() {
+                WebGPUMsg::DeviceLost { reason, .. } => reason,
+                WebGPUMsg::UncapturedError { device_id, error } => {
+                    warn!("Uncaptured error of device {:?}: {:?}", device_id, error);
+                    return;
+                },
+            };
+            let trusted = match trusted.take() {
+                Some(trusted) => trusted,
+                // A device is only lost once.
+                None => return,
+            };
+            let message = match reason {
+                WebGPUError::Destroyed => "The device was destroyed".to_owned(),
+                WebGPUError::DeviceLost => "The device was lost".to_owned(),
+                WebGPUError::Internal => "An internal error occurred".to_owned(),
+                reason => format!("The device was lost ({:?})", reason),
+            };
+            let result = task_source.queue_with_canceller(
+                task!(resolve_device_lost: move || {
+                    let promise = trusted.root();
+                    let info = GPUDeviceLostInfo::new(&promise.global(), DOMString::from(message));
+                    promise.resolve_native(&info);
+                }),
+                &canceller,
+            );
+            if let Err(err) = result {
+                error!("Failed to queue GPU device lost task: {:?}", err);
+            }
+        }),
+    );
+    lost_sender
+}
+
 impl GPUMethods for GPU {
     // https://gpuweb.github.io/gpuweb/#dom-gpu-requestadapter
     fn RequestAdapter(&self, options: &GPURequestAdapterOptions, comp: InRealm) -> Rc<Promise> {
@@ -122,6 +172,7 @@ impl GPUMethods for GPU {
             .send(ScriptMsg::RequestAdapter(
                 sender,
                 wgpu::instance::RequestAdapterOptions { power_preference },
+                options.forceFallbackAdapter,
                 ids,
             ))
             .is_err()
@@ -138,13 +189,20 @@ impl AsyncWGPUListener for GPU {
             WebGPUResponse::RequestAdapter {
                 adapter_name,
                 adapter_id,
+                extensions,
+                // GPUAdapter.limits and GPUAdapterInfo are not exposed yet.
+                vendor_id: _,
+                device_id: _,
+                limits: _,
+                is_fallback,
                 channel,
             } => {
                 let adapter = GPUAdapter::new(
                     &self.global(),
                     channel,
                     DOMString::from(format!("{} ({:?})", adapter_name, adapter_id.0.backend())),
-                    Heap::default(),
+                    extensions,
+                    is_fallback,
                     adapter_id,
                 );
                 promise.resolve_native(&adapter);
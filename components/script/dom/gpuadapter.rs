@@ -2,22 +2,25 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::GPUAdapterBinding::{
-    GPUAdapterMethods, GPUDeviceDescriptor,
+    GPUAdapterMethods, GPUDeviceDescriptor, GPUExtensions,
 };
 use crate::dom::bindings::error::Error;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
-use crate::dom::gpu::response_async;
 use crate::dom::gpu::AsyncWGPUListener;
+use crate::dom::gpu::{device_lost_async, response_async};
 use crate::dom::gpudevice::GPUDevice;
 use crate::dom::promise::Promise;
-use crate::realms::InRealm;
+use crate::realms::{enter_realm, InRealm};
 use crate::script_runtime::JSContext as SafeJSContext;
 use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
 use js::jsapi::{Heap, JSObject};
+use js::jsval::UndefinedValue;
 use std::ptr::NonNull;
 use std::rc::Rc;
 use webgpu::{wgpu, WebGPU, WebGPUAdapter, WebGPURequest, WebGPUResponse};
@@ -30,38 +33,71 @@ pub struct GPUAdapter {
     name: DOMString,
     #[ignore_malloc_size_of = "mozjs"]
     extensions: Heap<*mut JSObject>,
+    is_fallback: bool,
     adapter: WebGPUAdapter,
+    /// The `lost` promises of the devices requested but not created yet,
+    /// with the promise of their request.
+    #[ignore_malloc_size_of = "promises are hard"]
+    pending_lost_promises: DomRefCell<Vec<(Rc<Promise>, Rc<Promise>)>>,
 }
 
 impl GPUAdapter {
     pub fn new_inherited(
         channel: WebGPU,
         name: DOMString,
-        extensions: Heap<*mut JSObject>,
+        is_fallback: bool,
         adapter: WebGPUAdapter,
     ) -> GPUAdapter {
         GPUAdapter {
             reflector_: Reflector::new(),
             channel,
             name,
-            extensions,
+            extensions: Heap::default(),
+            is_fallback,
             adapter,
+            pending_lost_promises: DomRefCell::new(Vec::new()),
         }
     }
 
+    #[allow(unsafe_code)]
     pub fn new(
         global: &GlobalScope,
         channel: WebGPU,
         name: DOMString,
-        extensions: Heap<*mut JSObject>,
+        extensions: wgpu::instance::Extensions,
+        is_fallback: bool,
         adapter: WebGPUAdapter,
     ) -> DomRoot<GPUAdapter> {
-        reflect_dom_object(
+        let gpu_adapter = reflect_dom_object(
             Box::new(GPUAdapter::new_inherited(
-                channel, name, extensions, adapter,
+                channel,
+                name,
+                is_fallback,
+                adapter,
             )),
             global,
-        )
+        );
+
+        let extensions = GPUExtensions {
+            anisotropicFiltering: extensions.anisotropic_filtering,
+        };
+        let _ac = enter_realm(&*global);
+        let cx = global.get_cx();
+        unsafe {
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            extensions.to_jsval(*cx, value.handle_mut());
+            gpu_adapter.extensions.set(value.to_object());
+        }
+        gpu_adapter
+    }
+
+    /// Removes the `lost` promise of the device requested with `promise`.
+    fn take_lost_promise(&self, promise: &Rc<Promise>) -> Option<Rc<Promise>> {
+        let mut pending = self.pending_lost_promises.borrow_mut();
+        let index = pending
+            .iter()
+            .position(|(request, _)| Rc::ptr_eq(request, promise))?;
+        Some(pending.swap_remove(index).1)
     }
 }
 
@@ -76,6 +112,11 @@ impl GPUAdapterMethods for GPUAdapter {
         NonNull::new(self.extensions.get()).unwrap()
     }
 
+    // https://gpuweb.github.io/gpuweb/#dom-gpuadapter-isfallbackadapter
+    fn IsFallbackAdapter(&self) -> bool {
+        self.is_fallback
+    }
+
     /// https://gpuweb.github.io/gpuweb/#dom-gpuadapter-requestdevice
     fn RequestDevice(&self, descriptor: &GPUDeviceDescriptor, comp: InRealm) -> Rc<Promise> {
         let promise = Promise::new_in_current_realm(&self.global(), comp);
@@ -92,6 +133,8 @@ impl GPUAdapterMethods for GPUAdapter {
             .global()
             .wgpu_id_hub()
             .create_device_id(self.adapter.0.backend());
+        let lost_promise = Promise::new_in_current_realm(&self.global(), comp);
+        let lost_sender = device_lost_async(&lost_promise, &self.global());
         if self
             .channel
             .0
@@ -100,10 +143,15 @@ impl GPUAdapterMethods for GPUAdapter {
                 adapter_id: self.adapter,
                 descriptor: desc,
                 device_id: id,
+                lost_sender: Some(lost_sender),
             })
             .is_err()
         {
             promise.reject_error(Error::Operation);
+        } else {
+            self.pending_lost_promises
+                .borrow_mut()
+                .push((promise.clone(), lost_promise));
         }
         promise
     }
@@ -117,6 +165,9 @@ impl AsyncWGPUListener for GPUAdapter {
                 queue_id,
                 _descriptor,
             } => {
+                let lost_promise = self
+                    .take_lost_promise(promise)
+                    .unwrap_or_else(|| Promise::new(&self.global()));
                 let device = GPUDevice::new(
                     &self.global(),
                     self.channel.clone(),
@@ -125,10 +176,18 @@ impl AsyncWGPUListener for GPUAdapter {
                     Heap::default(),
                     device_id,
                     queue_id,
+                    lost_promise,
                 );
                 promise.resolve_native(&device);
             },
-            _ => promise.reject_error(Error::Operation),
+            _ => {
+                self.take_lost_promise(promise);
+                promise.reject_error(Error::Operation);
+            },
         }
     }
+
+    fn handle_error(&self, promise: &Rc<Promise>) {
+        self.take_lost_promise(promise);
+    }
 }
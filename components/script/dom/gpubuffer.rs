@@ -4,7 +4,7 @@
 
 use crate::dom::bindings::cell::{DomRefCell, Ref};
 use crate::dom::bindings::codegen::Bindings::GPUBufferBinding::{GPUBufferMethods, GPUBufferSize};
-use crate::dom::bindings::error::Error;
+use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
 use crate::dom::bindings::root::DomRoot;
@@ -14,6 +14,7 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::gpu::{response_async, AsyncWGPUListener};
 use crate::dom::promise::Promise;
 use crate::realms::InRealm;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
 use js::jsapi::{Heap, JSObject};
 use js::jsval::UndefinedValue;
@@ -21,10 +22,11 @@ use js::rust::jsapi_wrapped::{DetachArrayBuffer, IsPromiseObject, RejectPromise}
 use js::rust::MutableHandle;
 use js::typedarray::{ArrayBuffer, CreateWith};
 use std::cell::Cell;
-use std::ptr;
+use std::ptr::{self, NonNull};
 use std::rc::Rc;
 use webgpu::{
-    wgpu::resource::BufferUsage, WebGPU, WebGPUBuffer, WebGPUDevice, WebGPURequest, WebGPUResponse,
+    wgpu::resource::BufferUsage, HostMap, WebGPU, WebGPUBuffer, WebGPUDevice, WebGPURequest,
+    WebGPUResponse,
 };
 
 // https://gpuweb.github.io/gpuweb/#buffer-state
@@ -116,11 +118,96 @@ impl GPUBuffer {
     pub fn state(&self) -> Ref<GPUBufferState> {
         self.state.borrow()
     }
+
+    #[allow(unsafe_code)]
+    /// The steps shared by `mapReadAsync` and `mapWriteAsync`.
+    fn map_async(&self, host_map: HostMap, comp: InRealm) -> Rc<Promise> {
+        let (required_usage, pending_state) = match host_map {
+            HostMap::Read => (
+                BufferUsage::MAP_READ,
+                GPUBufferState::MappedPendingForReading,
+            ),
+            HostMap::Write => (
+                BufferUsage::MAP_WRITE,
+                GPUBufferState::MappedPendingForWriting,
+            ),
+        };
+        // Step 1 & 2
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        match *self.state.borrow() {
+            GPUBufferState::Unmapped => {
+                match BufferUsage::from_bits(self.usage) {
+                    Some(usage) => {
+                        if !usage.contains(required_usage) {
+                            // TODO: Record validation error on the current scope
+                            promise.reject_error(Error::Abort);
+                            return promise;
+                        };
+                    },
+                    None => {
+                        promise.reject_error(Error::Abort);
+                        return promise;
+                    },
+                }
+            },
+            _ => {
+                promise.reject_error(Error::Abort);
+                return promise;
+            },
+        }
+        // Step 3
+        self.mapping.set(*promise.promise_obj());
+        // Step 4
+        *self.state.borrow_mut() = pending_state;
+
+        // Step 5.1
+        // The new ArrayBuffer is zero-filled, which is what a mapping for writing must contain.
+        if unsafe {
+            ArrayBuffer::create(
+                *self.global().get_cx(),
+                CreateWith::Length(self.size as u32),
+                MutableHandle::from_raw(self.mapping.handle_mut()),
+            )
+        }
+        .is_err()
+        {
+            promise.reject_error(Error::Operation);
+            return promise;
+        }
+
+        let sender = response_async(&promise, self);
+        if self
+            .channel
+            .0
+            .send(WebGPURequest::BufferMapAsync {
+                sender,
+                device_id: self.device.0,
+                buffer_id: self.buffer.0,
+                host_map,
+                offset: 0,
+                size: self.size,
+            })
+            .is_err()
+        {
+            promise.reject_error(Error::Operation);
+            return promise;
+        }
+
+        // Step 6
+        promise
+    }
 }
 
 impl Drop for GPUBuffer {
     fn drop(&mut self) {
-        self.Destroy()
+        self.Destroy();
+        if let Err(e) = self
+            .channel
+            .0
+            .send(WebGPURequest::DropBuffer(self.buffer.0))
+        {
+            warn!("Failed to send WebGPURequest::DropBuffer ({})", e);
+        }
     }
 }
 
@@ -135,6 +222,19 @@ impl GPUBufferMethods for GPUBuffer {
                 // TODO: Record validation error on the current scope
                 return;
             },
+            // The promise of the pending mapping is rejected once the response comes in.
+            GPUBufferState::MappedPendingForReading => {},
+            GPUBufferState::MappedPendingForWriting => {
+                // Nothing was written yet, but the WebGPU thread has to forget the mapping.
+                self.channel
+                    .0
+                    .send(WebGPURequest::UnmapBuffer {
+                        device_id: self.device.0,
+                        buffer_id: self.id().0,
+                        array_buffer: vec![],
+                    })
+                    .unwrap();
+            },
             GPUBufferState::MappedForWriting => {
                 // Step 3.1
                 match ArrayBuffer::from(self.mapping.get()) {
@@ -189,70 +289,27 @@ impl GPUBufferMethods for GPUBuffer {
         *self.state.borrow_mut() = GPUBufferState::Destroyed;
     }
 
-    #[allow(unsafe_code)]
     /// https://gpuweb.github.io/gpuweb/#dom-gpubuffer-mapreadasync
     fn MapReadAsync(&self, comp: InRealm) -> Rc<Promise> {
-        // Step 1 & 2
-        let promise = Promise::new_in_current_realm(&self.global(), comp);
-        match *self.state.borrow() {
-            GPUBufferState::Unmapped => {
-                match BufferUsage::from_bits(self.usage) {
-                    Some(usage) => {
-                        if !usage.contains(BufferUsage::MAP_READ) {
-                            // TODO: Record validation error on the current scope
-                            promise.reject_error(Error::Abort);
-                            return promise;
-                        };
-                    },
-                    None => {
-                        promise.reject_error(Error::Abort);
-                        return promise;
-                    },
-                }
-            },
-            _ => {
-                promise.reject_error(Error::Abort);
-                return promise;
-            },
-        }
-        // Step 3
-        self.mapping.set(*promise.promise_obj());
-        // Step 4
-        *self.state.borrow_mut() = GPUBufferState::MappedPendingForReading;
+        self.map_async(HostMap::Read, comp)
+    }
 
-        // Step 5.1
-        if unsafe {
-            ArrayBuffer::create(
-                *self.global().get_cx(),
-                CreateWith::Length(self.size as u32),
-                MutableHandle::from_raw(self.mapping.handle_mut()),
-            )
-        }
-        .is_err()
-        {
-            promise.reject_error(Error::Operation);
-            return promise;
-        }
+    /// https://gpuweb.github.io/gpuweb/#dom-gpubuffer-mapwriteasync
+    fn MapWriteAsync(&self, comp: InRealm) -> Rc<Promise> {
+        self.map_async(HostMap::Write, comp)
+    }
 
-        let sender = response_async(&promise, self);
-        if self
-            .channel
-            .0
-            .send(WebGPURequest::MapReadAsync {
-                sender,
-                buffer_id: self.buffer.0,
-                device_id: self.device.0,
-                usage: self.usage,
-                size: self.size,
-            })
-            .is_err()
-        {
-            promise.reject_error(Error::Operation);
-            return promise;
+    /// https://gpuweb.github.io/gpuweb/#dom-gpubuffer-getmappedrange
+    fn GetMappedRange(&self, _cx: JSContext) -> Fallible<NonNull<JSObject>> {
+        match *self.state.borrow() {
+            GPUBufferState::MappedForReading | GPUBufferState::MappedForWriting => {},
+            _ => return Err(Error::Operation),
+        }
+        // The mapping is a promise while mapping is pending, and null once unmapped.
+        match ArrayBuffer::from(self.mapping.get()) {
+            Ok(_) => Ok(NonNull::new(self.mapping.get()).unwrap()),
+            Err(_) => Err(Error::Operation),
         }
-
-        // Step 6
-        promise
     }
 
     /// https://gpuweb.github.io/gpuweb/#dom-gpuobjectbase-label
@@ -270,18 +327,27 @@ impl AsyncWGPUListener for GPUBuffer {
     #[allow(unsafe_code)]
     fn handle_response(&self, response: WebGPUResponse, promise: &Rc<Promise>) {
         match response {
-            WebGPUResponse::MapReadAsync(bytes) => unsafe {
+            WebGPUResponse::BufferMapAsync { data } => {
+                let state = self.state.borrow().clone();
+                let mapped_state = match state {
+                    GPUBufferState::MappedPendingForReading => GPUBufferState::MappedForReading,
+                    GPUBufferState::MappedPendingForWriting => GPUBufferState::MappedForWriting,
+                    // Unmapped or destroyed while the mapping was pending.
+                    _ => return promise.reject_error(Error::Abort),
+                };
                 match ArrayBuffer::from(self.mapping.get()) {
                     Ok(mut array_buffer) => {
                         // Step 5.2
-                        array_buffer.update(&bytes);
+                        if let GPUBufferState::MappedForReading = mapped_state {
+                            unsafe { array_buffer.update(&data) };
+                        }
                         // Step 5.3
-                        *self.state.borrow_mut() = GPUBufferState::MappedForReading;
+                        *self.state.borrow_mut() = mapped_state;
                         // Step 5.4
                         promise.resolve_native(&array_buffer);
                     },
                     _ => promise.reject_error(Error::Operation),
-                };
+                }
             },
             _ => promise.reject_error(Error::Operation),
         }
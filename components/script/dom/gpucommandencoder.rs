@@ -17,7 +17,7 @@ use crate::dom::gpucomputepassencoder::GPUComputePassEncoder;
 use dom_struct::dom_struct;
 use ipc_channel::ipc;
 use std::collections::HashSet;
-use webgpu::{WebGPU, WebGPUCommandEncoder, WebGPURequest};
+use webgpu::{WebGPU, WebGPUCommandBuffer, WebGPUCommandEncoder, WebGPURequest};
 
 #[dom_struct]
 pub struct GPUCommandEncoder {
@@ -110,7 +110,12 @@ impl GPUCommandEncoderMethods for GPUCommandEncoder {
             })
             .expect("Failed to send Finish");
 
-        let buffer = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let buffer = receiver
+            .recv()
+            .ok()
+            .and_then(|response| response.ok())
+            .unwrap_or(WebGPUCommandBuffer(self.encoder.0));
         GPUCommandBuffer::new(
             &self.global(),
             self.channel.clone(),
@@ -9,18 +9,19 @@ use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::gpubindgroup::GPUBindGroup;
+use crate::dom::gpubuffer::GPUBuffer;
 use crate::dom::gpucomputepipeline::GPUComputePipeline;
 use dom_struct::dom_struct;
 use std::cell::RefCell;
 use webgpu::{
     wgpu::command::{
         compute_ffi::{
-            wgpu_compute_pass_dispatch, wgpu_compute_pass_set_bind_group,
-            wgpu_compute_pass_set_pipeline,
+            wgpu_compute_pass_dispatch, wgpu_compute_pass_dispatch_indirect,
+            wgpu_compute_pass_set_bind_group, wgpu_compute_pass_set_pipeline,
         },
         RawPass,
     },
-    WebGPU, WebGPUCommandEncoder, WebGPURequest,
+    WebGPU, WebGPUBindGroup, WebGPUBuffer, WebGPUCommandEncoder, WebGPURequest,
 };
 
 #[dom_struct]
@@ -31,6 +32,10 @@ pub struct GPUComputePassEncoder {
     label: DomRefCell<Option<DOMString>>,
     #[ignore_malloc_size_of = "defined in wgpu-core"]
     raw_pass: RefCell<Option<RawPass>>,
+    #[ignore_malloc_size_of = "defined in webgpu"]
+    bind_group_offsets: DomRefCell<Vec<(WebGPUBindGroup, usize)>>,
+    #[ignore_malloc_size_of = "defined in webgpu"]
+    indirect_buffers: DomRefCell<Vec<(WebGPUBuffer, u64)>>,
 }
 
 impl GPUComputePassEncoder {
@@ -40,6 +45,8 @@ impl GPUComputePassEncoder {
             reflector_: Reflector::new(),
             label: DomRefCell::new(None),
             raw_pass: RefCell::new(Some(RawPass::new_compute(parent.0))),
+            bind_group_offsets: DomRefCell::new(Vec::new()),
+            indirect_buffers: DomRefCell::new(Vec::new()),
         }
     }
 
@@ -74,6 +81,23 @@ impl GPUComputePassEncoderMethods for GPUComputePassEncoder {
         }
     }
 
+    #[allow(unsafe_code)]
+    /// https://gpuweb.github.io/gpuweb/#dom-gpucomputepassencoder-dispatchindirect
+    fn DispatchIndirect(&self, indirect_buffer: &GPUBuffer, indirect_offset: u64) {
+        if let Some(raw_pass) = self.raw_pass.borrow_mut().as_mut() {
+            unsafe {
+                wgpu_compute_pass_dispatch_indirect(
+                    raw_pass,
+                    indirect_buffer.id().0,
+                    indirect_offset,
+                )
+            };
+            self.indirect_buffers
+                .borrow_mut()
+                .push((indirect_buffer.id(), indirect_offset));
+        }
+    }
+
     #[allow(unsafe_code)]
     /// https://gpuweb.github.io/gpuweb/#dom-gpurenderpassencoder-endpass
     fn EndPass(&self) {
@@ -85,6 +109,18 @@ impl GPUComputePassEncoderMethods for GPUComputePassEncoder {
                 .send(WebGPURequest::RunComputePass {
                     command_encoder_id,
                     pass_data,
+                    bind_group_offsets: self
+                        .bind_group_offsets
+                        .borrow_mut()
+                        .drain(..)
+                        .map(|(bind_group, count)| (bind_group.0, count))
+                        .collect(),
+                    indirect_buffers: self
+                        .indirect_buffers
+                        .borrow_mut()
+                        .drain(..)
+                        .map(|(buffer, offset)| (buffer.0, offset))
+                        .collect(),
                 })
                 .unwrap();
         }
@@ -103,6 +139,9 @@ impl GPUComputePassEncoderMethods for GPUComputePassEncoder {
                     dynamic_offsets.len(),
                 )
             };
+            self.bind_group_offsets
+                .borrow_mut()
+                .push((*bind_group.id(), dynamic_offsets.len()));
         }
     }
 
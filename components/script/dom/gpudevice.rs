@@ -10,7 +10,9 @@ use crate::dom::bindings::codegen::Bindings::GPUBindGroupBinding::GPUBindGroupDe
 use crate::dom::bindings::codegen::Bindings::GPUBindGroupLayoutBinding::{
     GPUBindGroupLayoutBindings, GPUBindGroupLayoutDescriptor, GPUBindingType,
 };
-use crate::dom::bindings::codegen::Bindings::GPUBufferBinding::GPUBufferDescriptor;
+use crate::dom::bindings::codegen::Bindings::GPUBufferBinding::{
+    GPUBufferDescriptor, GPUBufferMethods,
+};
 use crate::dom::bindings::codegen::Bindings::GPUComputePipelineBinding::GPUComputePipelineDescriptor;
 use crate::dom::bindings::codegen::Bindings::GPUDeviceBinding::{
     GPUCommandEncoderDescriptor, GPUDeviceMethods,
@@ -33,6 +35,7 @@ use crate::dom::gpucomputepipeline::GPUComputePipeline;
 use crate::dom::gpupipelinelayout::GPUPipelineLayout;
 use crate::dom::gpuqueue::GPUQueue;
 use crate::dom::gpushadermodule::GPUShaderModule;
+use crate::dom::promise::Promise;
 use crate::script_runtime::JSContext as SafeJSContext;
 use dom_struct::dom_struct;
 use ipc_channel::ipc;
@@ -41,12 +44,17 @@ use js::jsval::{JSVal, ObjectValue};
 use js::typedarray::{ArrayBuffer, CreateWith};
 use std::collections::{HashMap, HashSet};
 use std::ptr::{self, NonNull};
+use std::rc::Rc;
 use webgpu::wgpu::binding_model::{
     BindGroupBinding, BindGroupLayoutBinding, BindingResource, BindingType, BufferBinding,
     ShaderStage,
 };
 use webgpu::wgpu::resource::{BufferDescriptor, BufferUsage};
-use webgpu::{WebGPU, WebGPUDevice, WebGPUQueue, WebGPURequest};
+use webgpu::{
+    WebGPU, WebGPUBindGroup, WebGPUBindGroupLayout, WebGPUBuffer, WebGPUCommandEncoder,
+    WebGPUComputePipeline, WebGPUDevice, WebGPUPipelineLayout, WebGPUQueue, WebGPURequest,
+    WebGPUShaderModule,
+};
 
 #[dom_struct]
 pub struct GPUDevice {
@@ -61,6 +69,8 @@ pub struct GPUDevice {
     label: DomRefCell<Option<DOMString>>,
     device: WebGPUDevice,
     default_queue: Dom<GPUQueue>,
+    #[ignore_malloc_size_of = "promises are hard"]
+    lost_promise: Rc<Promise>,
 }
 
 impl GPUDevice {
@@ -71,6 +81,7 @@ impl GPUDevice {
         limits: Heap<*mut JSObject>,
         device: WebGPUDevice,
         queue: &GPUQueue,
+        lost_promise: Rc<Promise>,
     ) -> GPUDevice {
         Self {
             eventtarget: EventTarget::new_inherited(),
@@ -81,6 +92,7 @@ impl GPUDevice {
             label: DomRefCell::new(None),
             device,
             default_queue: Dom::from_ref(queue),
+            lost_promise,
         }
     }
 
@@ -93,11 +105,18 @@ impl GPUDevice {
         limits: Heap<*mut JSObject>,
         device: WebGPUDevice,
         queue: WebGPUQueue,
+        lost_promise: Rc<Promise>,
     ) -> DomRoot<GPUDevice> {
         let queue = GPUQueue::new(global, channel.clone(), queue);
         reflect_dom_object(
             Box::new(GPUDevice::new_inherited(
-                channel, adapter, extensions, limits, device, &queue,
+                channel,
+                adapter,
+                extensions,
+                limits,
+                device,
+                &queue,
+                lost_promise,
             )),
             global,
         )
@@ -131,6 +150,54 @@ impl GPUDevice {
             )
         }
     }
+
+    /// Creates a buffer that stays mapped for writing until it is unmapped,
+    /// with a zeroed mapping of its whole size.
+    fn create_mapped_buffer(&self, descriptor: &GPUBufferDescriptor) -> DomRoot<GPUBuffer> {
+        let (valid, wgpu_descriptor) = self.validate_buffer_descriptor(descriptor);
+        let (sender, receiver) = ipc::channel().unwrap();
+        let buffer_id = self
+            .global()
+            .wgpu_id_hub()
+            .create_buffer_id(self.device.0.backend());
+        self.channel
+            .0
+            .send(WebGPURequest::CreateBufferMapped {
+                sender,
+                device_id: self.device.0,
+                buffer_id,
+                descriptor: wgpu_descriptor.clone(),
+            })
+            .expect("Failed to create WebGPU buffer");
+
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut js_array_buffer = ptr::null_mut::<JSObject>());
+        unsafe {
+            assert!(ArrayBuffer::create(
+                *cx,
+                CreateWith::Length(descriptor.size as u32),
+                js_array_buffer.handle_mut(),
+            )
+            .is_ok());
+        }
+
+        // TODO: Record the error in the current scope
+        let (buffer, valid) = match receiver.recv() {
+            Ok(Ok(buffer)) => (buffer, valid),
+            _ => (WebGPUBuffer(buffer_id), false),
+        };
+        GPUBuffer::new(
+            &self.global(),
+            self.channel.clone(),
+            buffer,
+            self.device,
+            GPUBufferState::MappedForWriting,
+            wgpu_descriptor.size,
+            wgpu_descriptor.usage.bits(),
+            valid,
+            RootedTraceableBox::from_box(Heap::boxed(js_array_buffer.get())),
+        )
+    }
 }
 
 impl GPUDeviceMethods for GPUDevice {
@@ -154,6 +221,21 @@ impl GPUDeviceMethods for GPUDevice {
         DomRoot::from_ref(&self.default_queue)
     }
 
+    /// https://gpuweb.github.io/gpuweb/#dom-gpudevice-destroy
+    fn Destroy(&self) {
+        self.channel
+            .0
+            .send(WebGPURequest::DestroyDevice {
+                device_id: self.device.0,
+            })
+            .unwrap();
+    }
+
+    /// https://gpuweb.github.io/gpuweb/#dom-gpudevice-lost
+    fn Lost(&self) -> Rc<Promise> {
+        self.lost_promise.clone()
+    }
+
     /// https://gpuweb.github.io/gpuweb/#dom-gpuobjectbase-label
     fn GetLabel(&self) -> Option<DOMString> {
         self.label.borrow().clone()
@@ -166,6 +248,9 @@ impl GPUDeviceMethods for GPUDevice {
 
     /// https://gpuweb.github.io/gpuweb/#dom-gpudevice-createbuffer
     fn CreateBuffer(&self, descriptor: &GPUBufferDescriptor) -> DomRoot<GPUBuffer> {
+        if descriptor.mappedAtCreation {
+            return self.create_mapped_buffer(descriptor);
+        }
         let (valid, wgpu_descriptor) = self.validate_buffer_descriptor(descriptor);
         let (sender, receiver) = ipc::channel().unwrap();
         let id = self
@@ -182,7 +267,11 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU buffer");
 
-        let buffer = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let (buffer, valid) = match receiver.recv() {
+            Ok(Ok(buffer)) => (buffer, valid),
+            _ => (WebGPUBuffer(id), false),
+        };
 
         GPUBuffer::new(
             &self.global(),
@@ -203,48 +292,13 @@ impl GPUDeviceMethods for GPUDevice {
         cx: SafeJSContext,
         descriptor: &GPUBufferDescriptor,
     ) -> Vec<JSVal> {
-        let (valid, wgpu_descriptor) = self.validate_buffer_descriptor(descriptor);
-        let (sender, receiver) = ipc::channel().unwrap();
-        let buffer_id = self
-            .global()
-            .wgpu_id_hub()
-            .create_buffer_id(self.device.0.backend());
-        self.channel
-            .0
-            .send(WebGPURequest::CreateBufferMapped {
-                sender,
-                device_id: self.device.0,
-                buffer_id,
-                descriptor: wgpu_descriptor.clone(),
-            })
-            .expect("Failed to create WebGPU buffer");
-
-        rooted!(in(*cx) let mut js_array_buffer = ptr::null_mut::<JSObject>());
-        unsafe {
-            assert!(ArrayBuffer::create(
-                *cx,
-                CreateWith::Length(descriptor.size as u32),
-                js_array_buffer.handle_mut(),
-            )
-            .is_ok());
-        }
-
-        let buffer = receiver.recv().unwrap();
-        let buff = GPUBuffer::new(
-            &self.global(),
-            self.channel.clone(),
-            buffer,
-            self.device,
-            GPUBufferState::MappedForWriting,
-            wgpu_descriptor.size,
-            wgpu_descriptor.usage.bits(),
-            valid,
-            RootedTraceableBox::from_box(Heap::boxed(js_array_buffer.get())),
-        );
-
+        let buffer = self.create_mapped_buffer(descriptor);
+        let mapping = buffer
+            .GetMappedRange(cx)
+            .expect("A buffer created mapped has a mapping");
         vec![
-            ObjectValue(buff.reflector().get_jsobject().get()),
-            ObjectValue(js_array_buffer.get()),
+            ObjectValue(buffer.reflector().get_jsobject().get()),
+            ObjectValue(mapping.as_ptr()),
         ]
     }
 
@@ -404,7 +458,11 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU BindGroupLayout");
 
-        let bgl = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let (bgl, valid) = match receiver.recv() {
+            Ok(Ok(bgl)) => (bgl, valid),
+            _ => (WebGPUBindGroupLayout(bind_group_layout_id), false),
+        };
 
         let binds = descriptor
             .bindings
@@ -483,7 +541,11 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU PipelineLayout");
 
-        let pipeline_layout = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let (pipeline_layout, valid) = match receiver.recv() {
+            Ok(Ok(pipeline_layout)) => (pipeline_layout, valid),
+            _ => (WebGPUPipelineLayout(pipeline_layout_id), false),
+        };
         GPUPipelineLayout::new(&self.global(), bind_group_layouts, pipeline_layout, valid)
     }
 
@@ -494,7 +556,11 @@ impl GPUDeviceMethods for GPUDevice {
 
         valid &= descriptor.bindings.iter().all(|bind| {
             let buffer_size = bind.resource.buffer.size();
-            let resource_size = bind.resource.size.unwrap_or(buffer_size);
+            // Without a size, the binding extends to the end of the buffer.
+            let resource_size = bind
+                .resource
+                .size
+                .unwrap_or(buffer_size.saturating_sub(bind.resource.offset));
             let length = bind.resource.offset.checked_add(resource_size);
             let usage = BufferUsage::from_bits(bind.resource.buffer.usage()).unwrap();
 
@@ -524,7 +590,12 @@ impl GPUDeviceMethods for GPUDevice {
                 resource: BindingResource::Buffer(BufferBinding {
                     buffer: bind.resource.buffer.id().0,
                     offset: bind.resource.offset,
-                    size: bind.resource.size.unwrap_or(bind.resource.buffer.size()),
+                    size: bind.resource.size.unwrap_or(
+                        bind.resource
+                            .buffer
+                            .size()
+                            .saturating_sub(bind.resource.offset),
+                    ),
                 }),
             })
             .collect::<Vec<_>>();
@@ -544,7 +615,11 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU BindGroup");
 
-        let bind_group = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let (bind_group, valid) = match receiver.recv() {
+            Ok(Ok(bind_group)) => (bind_group, valid),
+            _ => (WebGPUBindGroup(bind_group_id), false),
+        };
         GPUBindGroup::new(&self.global(), bind_group, valid)
     }
 
@@ -572,7 +647,12 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU ShaderModule");
 
-        let shader_module = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let shader_module = receiver
+            .recv()
+            .ok()
+            .and_then(|response| response.ok())
+            .unwrap_or(WebGPUShaderModule(program_id));
         GPUShaderModule::new(&self.global(), shader_module)
     }
 
@@ -601,7 +681,12 @@ impl GPUDeviceMethods for GPUDevice {
             })
             .expect("Failed to create WebGPU ComputePipeline");
 
-        let compute_pipeline = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let compute_pipeline = receiver
+            .recv()
+            .ok()
+            .and_then(|response| response.ok())
+            .unwrap_or(WebGPUComputePipeline(compute_pipeline_id));
         GPUComputePipeline::new(&self.global(), compute_pipeline)
     }
     /// https://gpuweb.github.io/gpuweb/#dom-gpudevice-createcommandencoder
@@ -622,7 +707,12 @@ impl GPUDeviceMethods for GPUDevice {
                 command_encoder_id,
             })
             .expect("Failed to create WebGPU command encoder");
-        let encoder = receiver.recv().unwrap();
+        // TODO: Record the error in the current scope
+        let encoder = receiver
+            .recv()
+            .ok()
+            .and_then(|response| response.ok())
+            .unwrap_or(WebGPUCommandEncoder(command_encoder_id));
 
         GPUCommandEncoder::new(&self.global(), self.channel.clone(), encoder)
     }
@@ -1375,6 +1375,9 @@ pub trait LayoutHTMLImageElementHelpers {
     #[allow(unsafe_code)]
     unsafe fn image_density(&self) -> Option<f64>;
 
+    #[allow(unsafe_code)]
+    unsafe fn image_is_broken(&self) -> bool;
+
     #[allow(unsafe_code)]
     unsafe fn image_data(&self) -> (Option<Arc<Image>>, Option<ImageMetadata>);
 
@@ -1419,6 +1422,18 @@ impl LayoutHTMLImageElementHelpers for LayoutDom<HTMLImageElement> {
             .clone()
     }
 
+    #[allow(unsafe_code)]
+    unsafe fn image_is_broken(&self) -> bool {
+        match (*self.unsafe_get())
+            .current_request
+            .borrow_for_layout()
+            .state
+        {
+            State::Broken => true,
+            State::Unavailable | State::PartiallyAvailable | State::CompletelyAvailable => false,
+        }
+    }
+
     #[allow(unsafe_code)]
     fn get_width(&self) -> LengthOrPercentageOrAuto {
         unsafe {
@@ -328,6 +328,7 @@ pub mod gpucommandencoder;
 pub mod gpucomputepassencoder;
 pub mod gpucomputepipeline;
 pub mod gpudevice;
+pub mod gpudevicelostinfo;
 pub mod gpupipelinelayout;
 pub mod gpuqueue;
 pub mod gpushadermodule;
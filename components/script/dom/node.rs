@@ -1330,6 +1330,7 @@ pub trait LayoutNodeHelpers {
     fn selection(&self) -> Option<Range<usize>>;
     fn image_url(&self) -> Option<ServoUrl>;
     fn image_density(&self) -> Option<f64>;
+    fn image_is_broken(&self) -> bool;
     fn image_data(&self) -> Option<(Option<StdArc<Image>>, Option<ImageMetadata>)>;
     fn canvas_data(&self) -> Option<HTMLCanvasData>;
     fn media_data(&self) -> Option<HTMLMediaData>;
@@ -1509,6 +1510,15 @@ impl LayoutNodeHelpers for LayoutDom<Node> {
         }
     }
 
+    #[allow(unsafe_code)]
+    fn image_is_broken(&self) -> bool {
+        unsafe {
+            self.downcast::<HTMLImageElement>()
+                .expect("not an image!")
+                .image_is_broken()
+        }
+    }
+
     fn canvas_data(&self) -> Option<HTMLCanvasData> {
         self.downcast::<HTMLCanvasElement>()
             .map(|canvas| canvas.data())
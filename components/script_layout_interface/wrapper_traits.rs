@@ -279,6 +279,9 @@ pub trait ThreadSafeLayoutNode:
     /// If this is an image element, returns its current-pixel-density. If this is not an image element, fails.
     fn image_density(&self) -> Option<f64>;
 
+    /// If this is an image element, returns whether its image failed to load. If this is not an image element, fails.
+    fn image_is_broken(&self) -> bool;
+
     /// If this is an image element, returns its image data. Otherwise, returns `None`.
     fn image_data(&self) -> Option<(Option<StdArc<Image>>, Option<ImageMetadata>)>;
 
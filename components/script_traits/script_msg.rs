@@ -276,10 +276,11 @@ pub enum ScriptMsg {
     /// Notifies the constellation about media session events
     /// (i.e. when there is metadata for the active media session, playback state changes...).
     MediaSessionEvent(PipelineId, MediaSessionEvent),
-    /// Create a WebGPU Adapter instance
+    /// Create a WebGPU Adapter instance, only picking a fallback adapter if the bool is true
     RequestAdapter(
         IpcSender<WebGPUResponseResult>,
         wgpu::instance::RequestAdapterOptions,
+        bool,
         SmallVec<[wgpu::id::AdapterId; 4]>,
     ),
 }
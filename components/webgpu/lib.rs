@@ -7,19 +7,28 @@ extern crate log;
 #[macro_use]
 pub extern crate wgpu_core as wgpu;
 
-use ipc_channel::ipc::{self, IpcReceiver, IpcSender, IpcSharedMemory};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender, IpcSharedMemory, TryRecvError};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use servo_config::pref;
 use smallvec::SmallVec;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
 use wgpu::{
-    binding_model::{BindGroupBinding, BindGroupLayoutBinding},
+    binding_model::{BindGroupBinding, BindGroupLayoutBinding, BindingResource, BindingType},
+    command::{BufferCopyView, TextureCopyView},
     id::{
         AdapterId, BindGroupId, BindGroupLayoutId, BufferId, CommandBufferId, CommandEncoderId,
-        ComputePipelineId, DeviceId, PipelineLayoutId, QueueId, ShaderModuleId,
+        ComputePipelineId, DeviceId, PipelineLayoutId, QueueId, SamplerId, ShaderModuleId,
+        TextureId, TextureViewId,
     },
     instance::{DeviceDescriptor, RequestAdapterOptions},
-    resource::BufferDescriptor,
-    BufferAddress,
+    resource::{
+        BufferDescriptor, SamplerDescriptor, TextureDescriptor, TextureFormat,
+        TextureViewDescriptor,
+    },
+    BufferAddress, Extent3d,
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -27,6 +36,12 @@ pub enum WebGPUResponse {
     RequestAdapter {
         adapter_name: String,
         adapter_id: WebGPUAdapter,
+        // PCI ids, the backend is that of `adapter_id`
+        vendor_id: u32,
+        device_id: u32,
+        extensions: wgpu::instance::Extensions,
+        limits: wgpu::instance::Limits,
+        is_fallback: bool,
         channel: WebGPU,
     },
     RequestDevice {
@@ -34,15 +49,103 @@ pub enum WebGPUResponse {
         queue_id: WebGPUQueue,
         _descriptor: DeviceDescriptor,
     },
-    MapReadAsync(IpcSharedMemory),
+    BufferMapAsync {
+        data: Vec<u8>,
+    },
 }
 
 pub type WebGPUResponseResult = Result<WebGPUResponse, String>;
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum WebGPUError {
+    /// The device was destroyed with `WebGPURequest::DestroyDevice`.
+    Destroyed,
+    /// The device was torn down because its creator stopped listening.
+    DeviceLost,
+    /// wgpu-core panicked while handling a request for the device, which was torn down.
+    Internal,
+    /// The device already owns `dom.webgpu.max_buffers_per_device` buffers.
+    ResourceLimitExceeded,
+    /// https://gpuweb.github.io/gpuweb/#gpuvalidationerror
+    Validation(String),
+}
+
+pub type WebGPUResult<T> = Result<T, WebGPUError>;
+
+/// Messages the WebGPU thread sends to script on its own accord.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum WebGPUMsg {
+    /// https://gpuweb.github.io/gpuweb/#dom-gpudevice-lost
+    DeviceLost {
+        device_id: DeviceId,
+        reason: WebGPUError,
+    },
+    /// An error of the device that no reply reports.
+    /// https://gpuweb.github.io/gpuweb/#eventdef-gpudevice-uncapturederror
+    UncapturedError {
+        device_id: DeviceId,
+        error: WebGPUError,
+    },
+}
+
+/// The direction of a `WebGPURequest::BufferMapAsync` mapping.
+/// https://gpuweb.github.io/gpuweb/#typedefdef-gpumapmodeflags
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum HostMap {
+    Read,
+    Write,
+}
+
+/// The limits a renderer is usually configured against, in one message.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct WebGPUDeviceCapabilities {
+    pub max_bind_groups: u32,
+    pub max_vertex_attributes: u32,
+    pub max_vertex_buffers: u32,
+    pub max_color_attachments: u32,
+}
+
+/// Alignment of buffer binding offsets, for both uniform and storage buffers.
+/// https://gpuweb.github.io/gpuweb/#dom-gpulimits-minuniformbufferoffsetalignment
+const BIND_BUFFER_ALIGNMENT: BufferAddress = 256;
+
+/// Size of the `x`, `y` and `z` workgroup counts read by `dispatchIndirect`.
+const DISPATCH_INDIRECT_SIZE: BufferAddress = 12;
+
+/// Alignment of the rows of buffers in copies between buffers and textures.
+/// https://gpuweb.github.io/gpuweb/#abstract-opdef-validating-gpuimagecopybuffer
+const BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// The first word of every SPIR-V module.
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+/// SPIR-V `OpEntryPoint`, and the execution model of compute shaders.
+/// https://www.khronos.org/registry/spir-v/specs/unified1/SPIRV.html#OpEntryPoint
+const SPIRV_OP_ENTRY_POINT: u32 = 15;
+const SPIRV_EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+
+/// wgpu-core has no limit for vertex attributes yet, this is the default of
+/// https://gpuweb.github.io/gpuweb/#dom-supported-limits-maxvertexattributes
+const MAX_VERTEX_ATTRIBUTES: u32 = 16;
+
+/// How many orphaned requests are kept for `WebGPURequest::HealthCheck`, older ones
+/// are forgotten.
+const MAX_ORPHANED_REQUESTS: usize = 64;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum WebGPURequest {
+    /// Maps `offset..offset + size` of the buffer. Reads reply with the contents of that
+    /// range. Writes reply with no data, script fills a zeroed mapping of its own that
+    /// `UnmapBuffer` flushes back to that range (and only that range) of the buffer.
+    BufferMapAsync {
+        sender: IpcSender<WebGPUResponseResult>,
+        device_id: DeviceId,
+        buffer_id: BufferId,
+        host_map: HostMap,
+        offset: BufferAddress,
+        size: BufferAddress,
+    },
     CommandEncoderFinish {
-        sender: IpcSender<WebGPUCommandBuffer>,
+        sender: IpcSender<WebGPUResult<WebGPUCommandBuffer>>,
         command_encoder_id: CommandEncoderId,
         // TODO(zakorgy): Serialize CommandBufferDescriptor in wgpu-core
         // wgpu::command::CommandBufferDescriptor,
@@ -55,40 +158,63 @@ pub enum WebGPURequest {
         destination_offset: BufferAddress,
         size: BufferAddress,
     },
+    CopyBufferToTexture {
+        sender: IpcSender<WebGPUResult<()>>,
+        command_encoder_id: CommandEncoderId,
+        source: BufferCopyView,
+        destination: TextureCopyView,
+        copy_size: Extent3d,
+    },
+    CopyTextureToBuffer {
+        sender: IpcSender<WebGPUResult<()>>,
+        command_encoder_id: CommandEncoderId,
+        source: TextureCopyView,
+        destination: BufferCopyView,
+        copy_size: Extent3d,
+    },
     CreateBindGroup {
-        sender: IpcSender<WebGPUBindGroup>,
+        sender: IpcSender<WebGPUResult<WebGPUBindGroup>>,
         device_id: DeviceId,
         bind_group_id: BindGroupId,
         bind_group_layout_id: BindGroupLayoutId,
         bindings: Vec<BindGroupBinding>,
     },
     CreateBindGroupLayout {
-        sender: IpcSender<WebGPUBindGroupLayout>,
+        sender: IpcSender<WebGPUResult<WebGPUBindGroupLayout>>,
         device_id: DeviceId,
         bind_group_layout_id: BindGroupLayoutId,
         bindings: Vec<BindGroupLayoutBinding>,
     },
     CreateBuffer {
-        sender: IpcSender<WebGPUBuffer>,
+        sender: IpcSender<WebGPUResult<WebGPUBuffer>>,
         device_id: DeviceId,
         buffer_id: BufferId,
         descriptor: BufferDescriptor,
     },
+    /// Creates a buffer holding `data`, which must fit in `descriptor.size`.
+    /// The rest of the buffer is zeroed.
+    CreateBufferInit {
+        sender: IpcSender<WebGPUResult<WebGPUBuffer>>,
+        device_id: DeviceId,
+        buffer_id: BufferId,
+        descriptor: BufferDescriptor,
+        data: IpcSharedMemory,
+    },
     CreateBufferMapped {
-        sender: IpcSender<WebGPUBuffer>,
+        sender: IpcSender<WebGPUResult<WebGPUBuffer>>,
         device_id: DeviceId,
         buffer_id: BufferId,
         descriptor: BufferDescriptor,
     },
     CreateCommandEncoder {
-        sender: IpcSender<WebGPUCommandEncoder>,
+        sender: IpcSender<WebGPUResult<WebGPUCommandEncoder>>,
         device_id: DeviceId,
         // TODO(zakorgy): Serialize CommandEncoderDescriptor in wgpu-core
         // wgpu::command::CommandEncoderDescriptor,
         command_encoder_id: CommandEncoderId,
     },
     CreateComputePipeline {
-        sender: IpcSender<WebGPUComputePipeline>,
+        sender: IpcSender<WebGPUResult<WebGPUComputePipeline>>,
         device_id: DeviceId,
         compute_pipeline_id: ComputePipelineId,
         pipeline_layout_id: PipelineLayoutId,
@@ -96,29 +222,81 @@ pub enum WebGPURequest {
         entry_point: String,
     },
     CreatePipelineLayout {
-        sender: IpcSender<WebGPUPipelineLayout>,
+        sender: IpcSender<WebGPUResult<WebGPUPipelineLayout>>,
         device_id: DeviceId,
         pipeline_layout_id: PipelineLayoutId,
         bind_group_layouts: Vec<BindGroupLayoutId>,
     },
+    CreateSampler {
+        sender: IpcSender<WebGPUResult<WebGPUSampler>>,
+        device_id: DeviceId,
+        sampler_id: SamplerId,
+        descriptor: SamplerDescriptor,
+    },
     CreateShaderModule {
-        sender: IpcSender<WebGPUShaderModule>,
+        sender: IpcSender<WebGPUResult<WebGPUShaderModule>>,
         device_id: DeviceId,
         program_id: ShaderModuleId,
         program: Vec<u32>,
     },
+    CreateTexture {
+        sender: IpcSender<WebGPUResult<WebGPUTexture>>,
+        device_id: DeviceId,
+        texture_id: TextureId,
+        descriptor: TextureDescriptor,
+    },
+    CreateTextureView {
+        sender: IpcSender<WebGPUResult<WebGPUTextureView>>,
+        device_id: DeviceId,
+        texture_id: TextureId,
+        texture_view_id: TextureViewId,
+        // `None` for a view of the whole texture, in its format
+        descriptor: Option<TextureViewDescriptor>,
+    },
     DestroyBuffer(BufferId),
+    /// Finishes the pending work of the device and destroys it. Later requests
+    /// for the device fail with `WebGPUError::Destroyed`.
+    DestroyDevice {
+        device_id: DeviceId,
+    },
+    /// Runs the mapping callbacks and frees the resources of finished work.
+    /// With `force_wait`, blocks until all submitted work is done.
+    DevicePoll {
+        device_id: DeviceId,
+        force_wait: bool,
+    },
+    /// Sent once script no longer holds the buffer, which was destroyed before.
+    DropBuffer(BufferId),
+    DropTextureView(TextureViewId),
     Exit(IpcSender<()>),
-    MapReadAsync {
-        sender: IpcSender<WebGPUResponseResult>,
-        buffer_id: BufferId,
+    GetDeviceCapabilities {
+        sender: IpcSender<WebGPUResult<WebGPUDeviceCapabilities>>,
+        device_id: DeviceId,
+    },
+    /// Replies with the number of bind groups the layout of the pipeline expects,
+    /// or 0 for an unknown pipeline.
+    GetPipelineBindGroupLayoutCount {
+        sender: IpcSender<u32>,
+        pipeline_id: ComputePipelineId,
+    },
+    /// Replies with the names of the last requests whose response could not be delivered
+    /// since the last health check, which script will never see the end of.
+    HealthCheck(IpcSender<Vec<String>>),
+    /// Like `CreateComputePipeline`, but only processed once no other request is pending,
+    /// so that compilation can be hidden in idle time.
+    PrecompileComputePipeline {
+        sender: IpcSender<WebGPUResult<WebGPUComputePipeline>>,
         device_id: DeviceId,
-        usage: u32,
-        size: u64,
+        compute_pipeline_id: ComputePipelineId,
+        pipeline_layout_id: PipelineLayoutId,
+        program_id: ShaderModuleId,
+        entry_point: String,
     },
     RequestAdapter {
         sender: IpcSender<WebGPUResponseResult>,
         options: RequestAdapterOptions,
+        /// https://gpuweb.github.io/gpuweb/#dom-gpurequestadapteroptions-forcefallbackadapter
+        force_fallback_adapter: bool,
         ids: SmallVec<[AdapterId; 4]>,
     },
     RequestDevice {
@@ -126,10 +304,17 @@ pub enum WebGPURequest {
         adapter_id: WebGPUAdapter,
         descriptor: DeviceDescriptor,
         device_id: DeviceId,
+        /// Notified once the device is lost, for whatever reason.
+        lost_sender: Option<IpcSender<WebGPUMsg>>,
     },
     RunComputePass {
         command_encoder_id: CommandEncoderId,
         pass_data: Vec<u8>,
+        /// The bind group and number of dynamic offsets of every
+        /// `setBindGroup` encoded in `pass_data`.
+        bind_group_offsets: Vec<(BindGroupId, usize)>,
+        /// The buffer and offset of every `dispatchIndirect` encoded in `pass_data`.
+        indirect_buffers: Vec<(BufferId, BufferAddress)>,
     },
     Submit {
         queue_id: QueueId,
@@ -140,6 +325,14 @@ pub enum WebGPURequest {
         buffer_id: BufferId,
         array_buffer: Vec<u8>,
     },
+    /// https://gpuweb.github.io/gpuweb/#dom-gpuqueue-writebuffer
+    WriteBuffer {
+        sender: IpcSender<WebGPUResult<()>>,
+        queue_id: QueueId,
+        buffer_id: BufferId,
+        buffer_offset: BufferAddress,
+        data: Vec<u8>,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -181,6 +374,15 @@ impl WebGPU {
     }
 }
 
+struct PrecompileComputePipeline {
+    sender: IpcSender<WebGPUResult<WebGPUComputePipeline>>,
+    device_id: DeviceId,
+    compute_pipeline_id: ComputePipelineId,
+    pipeline_layout_id: PipelineLayoutId,
+    program_id: ShaderModuleId,
+    entry_point: String,
+}
+
 struct WGPU {
     receiver: IpcReceiver<WebGPURequest>,
     sender: IpcSender<WebGPURequest>,
@@ -189,6 +391,44 @@ struct WGPU {
     devices: Vec<WebGPUDevice>,
     // Track invalid adapters https://gpuweb.github.io/gpuweb/#invalid
     _invalid_adapters: Vec<WebGPUAdapter>,
+    // Low priority requests, processed when the receiver has nothing pending
+    idle_requests: VecDeque<PrecompileComputePipeline>,
+    max_buffers_per_device: usize,
+    buffer_counts: HashMap<DeviceId, usize>,
+    buffer_devices: HashMap<BufferId, DeviceId>,
+    buffer_sizes: HashMap<BufferId, BufferAddress>,
+    buffer_usages: HashMap<BufferId, wgpu::resource::BufferUsage>,
+    // Buffers destroyed by script, whose ids must not reach wgpu-core again
+    destroyed_buffers: HashSet<BufferId>,
+    // Device of each command encoder, until it is finished
+    command_encoder_devices: HashMap<CommandEncoderId, DeviceId>,
+    // Command buffers are consumed by their first submission
+    submitted_command_buffers: HashSet<CommandBufferId>,
+    // Command encoders, and the command buffers they finish into, that recorded an
+    // invalid command, with the validation error that makes them invalid
+    invalid_command_buffers: HashMap<CommandBufferId, String>,
+    // Ranges of buffers mapped for writing, flushed on unmap
+    write_mappings: HashMap<BufferId, Range<BufferAddress>>,
+    // Binding types of each bind group layout, by binding number
+    bind_group_layout_types: HashMap<BindGroupLayoutId, HashMap<u32, BindingType>>,
+    // Number of bindings with a dynamic offset of each bind group layout and bind group
+    layout_dynamic_offset_counts: HashMap<BindGroupLayoutId, usize>,
+    bind_group_dynamic_offset_counts: HashMap<BindGroupId, usize>,
+    // Whether each sampler is a comparison sampler
+    comparison_samplers: HashMap<SamplerId, bool>,
+    // Number of bind group layouts of each pipeline layout, and the layout of each pipeline
+    bind_group_layout_counts: HashMap<PipelineLayoutId, u32>,
+    compute_pipeline_layouts: HashMap<ComputePipelineId, PipelineLayoutId>,
+    texture_formats: HashMap<TextureId, TextureFormat>,
+    texture_view_devices: HashMap<TextureViewId, DeviceId>,
+    // Execution model and name of the entry points of each shader module
+    shader_entry_points: HashMap<ShaderModuleId, Vec<(u32, String)>>,
+    // Requests whose response could not be sent, reported by `WebGPURequest::HealthCheck`
+    orphaned_requests: VecDeque<String>,
+    // Devices that can no longer be used, and the error reported for them
+    torn_down_devices: HashMap<DeviceId, WebGPUError>,
+    // Where the `WebGPUMsg`s of each device go
+    device_lost_senders: HashMap<DeviceId, IpcSender<WebGPUMsg>>,
 }
 
 impl WGPU {
@@ -200,387 +440,1741 @@ impl WGPU {
             adapters: Vec::new(),
             devices: Vec::new(),
             _invalid_adapters: Vec::new(),
+            idle_requests: VecDeque::new(),
+            max_buffers_per_device: pref!(dom.webgpu.max_buffers_per_device).max(0) as usize,
+            buffer_counts: HashMap::new(),
+            buffer_devices: HashMap::new(),
+            buffer_sizes: HashMap::new(),
+            buffer_usages: HashMap::new(),
+            destroyed_buffers: HashSet::new(),
+            command_encoder_devices: HashMap::new(),
+            submitted_command_buffers: HashSet::new(),
+            invalid_command_buffers: HashMap::new(),
+            write_mappings: HashMap::new(),
+            bind_group_layout_types: HashMap::new(),
+            layout_dynamic_offset_counts: HashMap::new(),
+            bind_group_dynamic_offset_counts: HashMap::new(),
+            comparison_samplers: HashMap::new(),
+            bind_group_layout_counts: HashMap::new(),
+            compute_pipeline_layouts: HashMap::new(),
+            texture_formats: HashMap::new(),
+            texture_view_devices: HashMap::new(),
+            shader_entry_points: HashMap::new(),
+            orphaned_requests: VecDeque::new(),
+            torn_down_devices: HashMap::new(),
+            device_lost_senders: HashMap::new(),
+        }
+    }
+
+    /// Fails with the reason `device_id` was torn down, if it was.
+    fn check_device(&self, device_id: DeviceId) -> WebGPUResult<()> {
+        match self.torn_down_devices.get(&device_id) {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Accounts for a new buffer owned by `device_id`, unless the device already
+    /// reached the `dom.webgpu.max_buffers_per_device` limit. Buffers are the only
+    /// resources script can destroy, so they are the only ones worth counting.
+    fn reserve_buffer(&mut self, device_id: DeviceId) -> WebGPUResult<()> {
+        self.check_device(device_id)?;
+        let count = self.buffer_counts.entry(device_id).or_insert(0);
+        if *count >= self.max_buffers_per_device {
+            warn!(
+                "Device {:?} reached the limit of {} buffers",
+                device_id, self.max_buffers_per_device
+            );
+            return Err(WebGPUError::ResourceLimitExceeded);
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn release_buffer(&mut self, device_id: DeviceId) {
+        if let Some(count) = self.buffer_counts.get_mut(&device_id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Sends the response to a request. If script is no longer listening, the request is
+    /// recorded for `WebGPURequest::HealthCheck`, and `false` is returned.
+    fn respond<T: serde::Serialize>(
+        &mut self,
+        sender: &IpcSender<T>,
+        response: T,
+        request: &str,
+    ) -> bool {
+        match sender.send(response) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(
+                    "Failed to send response to WebGPURequest::{} ({})",
+                    request, e
+                );
+                if self.orphaned_requests.len() == MAX_ORPHANED_REQUESTS {
+                    self.orphaned_requests.pop_front();
+                }
+                self.orphaned_requests.push_back(request.to_owned());
+                false
+            },
+        }
+    }
+
+    /// Runs a wgpu-core creation call for `device_id`. wgpu-core reports invalid
+    /// descriptors by panicking, so the panic message becomes a validation error for
+    /// script. As with `call_wgpu`, the state of the device is unknown after a panic,
+    /// so the device is torn down, with that error as the reason.
+    fn create_validated<T>(
+        &mut self,
+        device_id: DeviceId,
+        create: impl FnOnce(&wgpu::hub::Global<()>) -> T,
+    ) -> WebGPUResult<T> {
+        let global = &self.global;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| create(global)));
+        result.map_err(|payload| {
+            let error = WebGPUError::Validation(panic_message(payload, "invalid descriptor"));
+            self.tear_down_device(device_id, error.clone());
+            error
+        })
+    }
+
+    /// Runs a buffer creation call with `create_validated`, within the buffer limit
+    /// of `device_id`.
+    fn create_buffer_validated(
+        &mut self,
+        device_id: DeviceId,
+        create: impl FnOnce(&wgpu::hub::Global<()>) -> WebGPUBuffer,
+    ) -> WebGPUResult<WebGPUBuffer> {
+        self.reserve_buffer(device_id)?;
+        let result = self.create_validated(device_id, create);
+        if result.is_err() {
+            self.release_buffer(device_id);
+        }
+        result
+    }
+
+    /// Calls into wgpu-core, which panics on the errors it does not report otherwise.
+    /// Only the global is exposed to such a panic, and since its state is unknown
+    /// afterwards, the device of the call (if known) is torn down and the call fails
+    /// with `WebGPUError::Internal`.
+    fn call_wgpu<T>(
+        &mut self,
+        device_id: Option<DeviceId>,
+        call: impl FnOnce(&wgpu::hub::Global<()>) -> T,
+    ) -> WebGPUResult<T> {
+        let global = &self.global;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| call(global)));
+        result.map_err(|payload| {
+            warn!(
+                "wgpu-core panicked: {}",
+                panic_message(payload, "no message")
+            );
+            if let Some(device_id) = device_id {
+                self.tear_down_device(device_id, WebGPUError::Internal);
+            }
+            WebGPUError::Internal
+        })
+    }
+
+    /// Reports an error to the device itself, for script to surface even when
+    /// the request that caused it has no sender or the sender was dropped.
+    fn report_error(&self, device_id: DeviceId, error: WebGPUError) {
+        if let Some(sender) = self.device_lost_senders.get(&device_id) {
+            let msg = WebGPUMsg::UncapturedError { device_id, error };
+            if let Err(e) = sender.send(msg) {
+                warn!("Failed to send WebGPUMsg::UncapturedError ({})", e);
+            }
+        }
+    }
+
+    /// Destroys a device and forgets about it, so that resources can no longer be
+    /// created for it. Later requests for the device fail with `reason`.
+    fn tear_down_device(&mut self, device_id: DeviceId, reason: WebGPUError) {
+        if self.devices.iter().any(|device| device.0 == device_id) {
+            self.devices.retain(|device| device.0 != device_id);
+            let global = &self.global;
+            let destroyed = panic::catch_unwind(AssertUnwindSafe(
+                || gfx_select!(device_id => global.device_destroy(device_id)),
+            ));
+            if destroyed.is_err() {
+                warn!("wgpu-core panicked while destroying device {:?}", device_id);
+            }
+        }
+        self.buffer_counts.remove(&device_id);
+        if let Some(sender) = self.device_lost_senders.remove(&device_id) {
+            let msg = WebGPUMsg::DeviceLost {
+                device_id,
+                reason: reason.clone(),
+            };
+            if let Err(e) = sender.send(msg) {
+                warn!("Failed to send WebGPUMsg::DeviceLost ({})", e);
+            }
+        }
+        self.torn_down_devices.insert(device_id, reason);
+    }
+
+    /// A software adapter out of `ids`, reusing one of the `known` adapters if possible.
+    /// `enumerate_adapters` would register every adapter of a backend under the one id
+    /// script gave for it, so only the adapter wgpu-core picks gets registered, and it
+    /// is destroyed again unless it is a software one.
+    fn pick_fallback_adapter(
+        global: &wgpu::hub::Global<()>,
+        known: &[WebGPUAdapter],
+        options: &RequestAdapterOptions,
+        ids: &[AdapterId],
+    ) -> Option<AdapterId> {
+        let is_fallback = |&id: &AdapterId| {
+            let info = gfx_select!(id => global.adapter_get_info(id));
+            info.device_type == wgpu::instance::DeviceType::Cpu
+        };
+        let known = known
+            .iter()
+            .map(|adapter| adapter.0)
+            .filter(|id| ids.contains(id))
+            .collect::<Vec<_>>();
+        if !known.is_empty() {
+            // Picking again would register another adapter over a known one.
+            return known.into_iter().find(is_fallback);
+        }
+        let id = global.pick_adapter(
+            options,
+            wgpu::instance::AdapterInputs::IdSet(ids, |id| id.backend()),
+        )?;
+        if is_fallback(&id) {
+            return Some(id);
+        }
+        gfx_select!(id => global.adapter_destroy(id));
+        None
+    }
+
+    /// Fails for a buffer destroyed with `WebGPURequest::DestroyBuffer`, rather than
+    /// letting wgpu-core fail somewhere deeper without telling script.
+    fn check_not_destroyed(&self, buffer_id: BufferId) -> Result<(), String> {
+        if self.destroyed_buffers.contains(&buffer_id) {
+            return Err(format!("buffer {:?} was destroyed", buffer_id));
+        }
+        Ok(())
+    }
+
+    /// Checks that every buffer binding is an aligned range of a live buffer,
+    /// and that samplers match their layout entry,
+    /// before wgpu-core or the driver gets to see it.
+    fn validate_bind_group_bindings(
+        &self,
+        layout_id: BindGroupLayoutId,
+        bindings: &[BindGroupBinding],
+    ) -> WebGPUResult<()> {
+        for binding in bindings {
+            let buffer_binding = match binding.resource {
+                BindingResource::Buffer(ref buffer_binding) => buffer_binding,
+                BindingResource::Sampler(sampler_id) => {
+                    self.validate_sampler_binding(layout_id, binding.binding, sampler_id)?;
+                    continue;
+                },
+                _ => continue,
+            };
+            self.check_not_destroyed(buffer_binding.buffer)
+                .map_err(|e| {
+                    WebGPUError::Validation(format!("binding {}: {}", binding.binding, e))
+                })?;
+            let buffer_size = match self.buffer_sizes.get(&buffer_binding.buffer) {
+                Some(size) => *size,
+                None => {
+                    return Err(WebGPUError::Validation(format!(
+                        "binding {}: invalid buffer",
+                        binding.binding
+                    )))
+                },
+            };
+            if buffer_binding.offset % BIND_BUFFER_ALIGNMENT != 0 {
+                return Err(WebGPUError::Validation(format!(
+                    "binding {}: offset {} is not a multiple of {}",
+                    binding.binding, buffer_binding.offset, BIND_BUFFER_ALIGNMENT
+                )));
+            }
+            match buffer_binding.offset.checked_add(buffer_binding.size) {
+                Some(end) if end <= buffer_size => {},
+                _ => {
+                    return Err(WebGPUError::Validation(format!(
+                    "binding {}: {} bytes at offset {} are out of bounds of a buffer of {} bytes",
+                    binding.binding, buffer_binding.size, buffer_binding.offset, buffer_size
+                )))
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// A comparison sampler can only be bound to a comparison sampler entry, and the other
+    /// way around, since shaders sample them differently.
+    fn validate_sampler_binding(
+        &self,
+        layout_id: BindGroupLayoutId,
+        binding: u32,
+        sampler_id: SamplerId,
+    ) -> WebGPUResult<()> {
+        let is_comparison = match self.comparison_samplers.get(&sampler_id) {
+            Some(is_comparison) => *is_comparison,
+            None => {
+                return Err(WebGPUError::Validation(format!(
+                    "binding {}: invalid sampler",
+                    binding
+                )))
+            },
+        };
+        let ty = self
+            .bind_group_layout_types
+            .get(&layout_id)
+            .and_then(|types| types.get(&binding));
+        match (ty, is_comparison) {
+            (Some(BindingType::Sampler), false) | (Some(BindingType::ComparisonSampler), true) => {
+                Ok(())
+            },
+            (Some(BindingType::Sampler), true) => Err(WebGPUError::Validation(format!(
+                "binding {}: comparison sampler used for a non-comparison sampler entry",
+                binding
+            ))),
+            (Some(BindingType::ComparisonSampler), false) => Err(WebGPUError::Validation(format!(
+                "binding {}: non-comparison sampler used for a comparison sampler entry",
+                binding
+            ))),
+            _ => Err(WebGPUError::Validation(format!(
+                "binding {}: the layout has no sampler entry for this binding",
+                binding
+            ))),
         }
     }
 
-    fn deinit(self) {
-        self.global.delete()
+    /// Checks that the shader module has an entry point with this name for the stage,
+    /// so that a typo is reported as such rather than as a pipeline creation failure.
+    fn validate_entry_point(
+        &self,
+        program_id: ShaderModuleId,
+        execution_model: u32,
+        entry_point: &str,
+    ) -> WebGPUResult<()> {
+        let entry_points = match self.shader_entry_points.get(&program_id) {
+            Some(entry_points) => entry_points,
+            None => return Err(WebGPUError::Validation("invalid shader module".to_owned())),
+        };
+        if entry_points
+            .iter()
+            .any(|&(model, ref name)| model == execution_model && name == entry_point)
+        {
+            return Ok(());
+        }
+        let available = entry_points
+            .iter()
+            .filter(|&&(model, _)| model == execution_model)
+            .map(|&(_, ref name)| format!("\"{}\"", name))
+            .collect::<Vec<_>>();
+        Err(WebGPUError::Validation(format!(
+            "the shader module has no entry point named \"{}\" for this stage (found: {})",
+            entry_point,
+            if available.is_empty() {
+                "none".to_owned()
+            } else {
+                available.join(", ")
+            }
+        )))
+    }
+
+    /// Checks a copy between a buffer and a texture before it is recorded,
+    /// since wgpu-core does not report invalid copies.
+    fn validate_buffer_texture_copy(
+        &self,
+        buffer: &BufferCopyView,
+        buffer_usage: wgpu::resource::BufferUsage,
+        texture: &TextureCopyView,
+        copy_size: &Extent3d,
+    ) -> WebGPUResult<()> {
+        self.check_not_destroyed(buffer.buffer)
+            .map_err(WebGPUError::Validation)?;
+        let (usage, buffer_size) = match (
+            self.buffer_usages.get(&buffer.buffer),
+            self.buffer_sizes.get(&buffer.buffer),
+        ) {
+            (Some(usage), Some(size)) => (*usage, *size),
+            _ => return Err(WebGPUError::Validation("invalid buffer".to_owned())),
+        };
+        if !usage.contains(buffer_usage) {
+            return Err(WebGPUError::Validation(format!(
+                "buffer usage does not include {:?}",
+                buffer_usage
+            )));
+        }
+        if buffer.row_pitch % BYTES_PER_ROW_ALIGNMENT != 0 {
+            return Err(WebGPUError::Validation(format!(
+                "bytes per row ({}) is not a multiple of {}",
+                buffer.row_pitch, BYTES_PER_ROW_ALIGNMENT
+            )));
+        }
+        let format = match self.texture_formats.get(&texture.texture) {
+            Some(format) => *format,
+            None => return Err(WebGPUError::Validation("invalid texture".to_owned())),
+        };
+        let required = required_bytes_in_copy(
+            format,
+            buffer.offset,
+            buffer.row_pitch,
+            buffer.image_height,
+            copy_size,
+        )
+        .map_err(WebGPUError::Validation)?;
+        if required > buffer_size {
+            return Err(WebGPUError::Validation(format!(
+                "the copy spans {} bytes, but the buffer only has {}",
+                required, buffer_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that the buffer can be mapped in this direction, over this range.
+    fn validate_buffer_map(
+        &self,
+        buffer_id: BufferId,
+        host_map: HostMap,
+        offset: BufferAddress,
+        size: BufferAddress,
+    ) -> Result<(), String> {
+        self.check_not_destroyed(buffer_id)
+            .map_err(|e| format!("BufferMapAsync: {}", e))?;
+        let (usage, buffer_size) = match (
+            self.buffer_usages.get(&buffer_id),
+            self.buffer_sizes.get(&buffer_id),
+        ) {
+            (Some(usage), Some(size)) => (*usage, *size),
+            _ => return Err("BufferMapAsync: Invalid buffer".to_owned()),
+        };
+        let required = match host_map {
+            HostMap::Read => wgpu::resource::BufferUsage::MAP_READ,
+            HostMap::Write => wgpu::resource::BufferUsage::MAP_WRITE,
+        };
+        if !usage.contains(required) {
+            return Err(format!(
+                "BufferMapAsync: Buffer usage does not include {:?}",
+                required
+            ));
+        }
+        if offset
+            .checked_add(size)
+            .map_or(true, |end| end > buffer_size)
+        {
+            return Err(format!(
+                "BufferMapAsync: {} bytes at offset {} are out of bounds of {} bytes",
+                size, offset, buffer_size
+            ));
+        }
+        if self.write_mappings.contains_key(&buffer_id) {
+            return Err("BufferMapAsync: Buffer is already mapped".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Checks a `WebGPURequest::WriteBuffer` of `size` bytes at `offset` of the buffer.
+    /// https://gpuweb.github.io/gpuweb/#dom-gpuqueue-writebuffer
+    fn validate_buffer_write(
+        &self,
+        buffer_id: BufferId,
+        offset: BufferAddress,
+        size: BufferAddress,
+    ) -> Result<(), String> {
+        self.check_not_destroyed(buffer_id)?;
+        let (usage, buffer_size) = match (
+            self.buffer_usages.get(&buffer_id),
+            self.buffer_sizes.get(&buffer_id),
+        ) {
+            (Some(usage), Some(size)) => (*usage, *size),
+            _ => return Err("invalid buffer".to_owned()),
+        };
+        if !usage.contains(wgpu::resource::BufferUsage::COPY_DST) {
+            return Err("buffer usage does not include COPY_DST".to_owned());
+        }
+        if offset % 4 != 0 || size % 4 != 0 {
+            return Err(format!(
+                "the offset ({}) and size ({}) are not multiples of 4",
+                offset, size
+            ));
+        }
+        if offset
+            .checked_add(size)
+            .map_or(true, |end| end > buffer_size)
+        {
+            return Err(format!(
+                "{} bytes at offset {} are out of bounds of a buffer of {} bytes",
+                size, offset, buffer_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the bind groups and indirect buffers used by a compute pass,
+    /// which wgpu-core only finds out about while recording it.
+    fn validate_compute_pass(
+        &self,
+        bind_group_offsets: &[(BindGroupId, usize)],
+        indirect_buffers: &[(BufferId, BufferAddress)],
+    ) -> Result<(), String> {
+        for &(id, count) in bind_group_offsets {
+            match self.bind_group_dynamic_offset_counts.get(&id) {
+                Some(&expected) if expected == count => {},
+                Some(&expected) => {
+                    return Err(format!(
+                        "bind group {:?} was set with {} dynamic offsets, expected {}",
+                        id, count, expected
+                    ));
+                },
+                None => return Err(format!("invalid bind group {:?}", id)),
+            }
+        }
+        for &(id, offset) in indirect_buffers {
+            self.check_not_destroyed(id)?;
+            let (usage, size) = match (self.buffer_usages.get(&id), self.buffer_sizes.get(&id)) {
+                (Some(usage), Some(size)) => (*usage, *size),
+                _ => return Err(format!("invalid indirect buffer {:?}", id)),
+            };
+            if !usage.contains(wgpu::resource::BufferUsage::INDIRECT) {
+                return Err(format!("buffer {:?} does not have the INDIRECT usage", id));
+            }
+            if offset % 4 != 0 {
+                return Err(format!("indirect offset {} is not a multiple of 4", offset));
+            }
+            if offset
+                .checked_add(DISPATCH_INDIRECT_SIZE)
+                .map_or(true, |end| end > size)
+            {
+                return Err(format!(
+                    "indirect offset {} is out of bounds of {} bytes",
+                    offset, size
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the pending work and destroys every device and adapter before the global,
+    /// answering deferred requests so that script does not wait for them forever.
+    fn deinit(mut self) {
+        let idle_requests = std::mem::replace(&mut self.idle_requests, VecDeque::new());
+        for request in idle_requests {
+            self.respond(
+                &request.sender,
+                Err(WebGPUError::DeviceLost),
+                "PrecompileComputePipeline",
+            );
+        }
+        self.write_mappings.clear();
+        for (device_id, sender) in self.device_lost_senders.drain() {
+            let msg = WebGPUMsg::DeviceLost {
+                device_id,
+                reason: WebGPUError::DeviceLost,
+            };
+            if let Err(e) = sender.send(msg) {
+                warn!("Failed to send WebGPUMsg::DeviceLost ({})", e);
+            }
+        }
+        // A panic for one device must not keep the others from being cleaned up.
+        let devices = std::mem::replace(&mut self.devices, Vec::new());
+        for device in &devices {
+            let _ = self.call_wgpu(
+                None,
+                |global| gfx_select!(device.0 => global.device_poll(device.0, true)),
+            );
+        }
+        for device in devices {
+            let _ = self.call_wgpu(
+                None,
+                |global| gfx_select!(device.0 => global.device_destroy(device.0)),
+            );
+        }
+        let adapters = std::mem::replace(&mut self.adapters, Vec::new());
+        for adapter in adapters {
+            let _ = self.call_wgpu(
+                None,
+                |global| gfx_select!(adapter.0 => global.adapter_destroy(adapter.0)),
+            );
+        }
+        let global = self.global;
+        if panic::catch_unwind(AssertUnwindSafe(|| global.delete())).is_err() {
+            warn!("wgpu-core panicked while deleting the global");
+        }
+    }
+
+    fn create_compute_pipeline(
+        &mut self,
+        device_id: DeviceId,
+        compute_pipeline_id: ComputePipelineId,
+        pipeline_layout_id: PipelineLayoutId,
+        program_id: ShaderModuleId,
+        entry_point: String,
+    ) -> WebGPUResult<WebGPUComputePipeline> {
+        let entry_point = match std::ffi::CString::new(entry_point) {
+            Ok(entry_point) => entry_point,
+            Err(_) => {
+                return Err(WebGPUError::Validation(
+                    "the entry point name contains a NUL character".to_owned(),
+                ));
+            },
+        };
+        let descriptor = wgpu_core::pipeline::ComputePipelineDescriptor {
+            layout: pipeline_layout_id,
+            compute_stage: wgpu_core::pipeline::ProgrammableStageDescriptor {
+                module: program_id,
+                entry_point: entry_point.as_ptr(),
+            },
+        };
+        let cp_id = self.create_validated(device_id, |global| {
+            gfx_select!(compute_pipeline_id =>
+                global.device_create_compute_pipeline(device_id, &descriptor, compute_pipeline_id))
+        })?;
+        self.compute_pipeline_layouts
+            .insert(compute_pipeline_id, pipeline_layout_id);
+        Ok(WebGPUComputePipeline(cp_id))
+    }
+
+    /// Returns the next request to process, running idle work while waiting for one.
+    fn next_request(&mut self) -> Option<WebGPURequest> {
+        loop {
+            if self.idle_requests.is_empty() {
+                return self.receiver.recv().ok();
+            }
+            match self.receiver.try_recv() {
+                Ok(msg) => return Some(msg),
+                Err(TryRecvError::Empty) => {
+                    let PrecompileComputePipeline {
+                        sender,
+                        device_id,
+                        compute_pipeline_id,
+                        pipeline_layout_id,
+                        program_id,
+                        entry_point,
+                    } = self.idle_requests.pop_front().unwrap();
+                    let result = self
+                        .validate_entry_point(
+                            program_id,
+                            SPIRV_EXECUTION_MODEL_GL_COMPUTE,
+                            &entry_point,
+                        )
+                        .and_then(|()| self.check_device(device_id))
+                        .and_then(|()| {
+                            self.create_compute_pipeline(
+                                device_id,
+                                compute_pipeline_id,
+                                pipeline_layout_id,
+                                program_id,
+                                entry_point,
+                            )
+                        });
+                    self.respond(&sender, result, "PrecompileComputePipeline");
+                },
+                Err(TryRecvError::IpcError(_)) => return None,
+            }
+        }
     }
 
     fn run(mut self) {
-        while let Ok(msg) = self.receiver.recv() {
-            match msg {
-                WebGPURequest::CommandEncoderFinish {
-                    sender,
-                    command_encoder_id,
-                } => {
-                    let global = &self.global;
+        while let Some(msg) = self.next_request() {
+            if let WebGPURequest::Exit(sender) = msg {
+                self.deinit();
+                if let Err(e) = sender.send(()) {
+                    warn!("Failed to send response to WebGPURequest::Exit ({})", e)
+                }
+                return;
+            }
+            self.handle_request(msg);
+        }
+    }
+
+    /// Handles every request but `Exit`. Returning early only ends the handling of
+    /// this request, `run` keeps serving the next ones. Calls into wgpu-core go through
+    /// `create_validated` or `call_wgpu`, so that a panic in wgpu-core only costs the
+    /// device it happened on, and the request still gets a response.
+    fn handle_request(&mut self, msg: WebGPURequest) {
+        match msg {
+            WebGPURequest::BufferMapAsync {
+                sender,
+                device_id,
+                buffer_id,
+                host_map,
+                offset,
+                size,
+            } => {
+                if let Err(e) = self.validate_buffer_map(buffer_id, host_map, offset, size) {
+                    self.respond(&sender, Err(e), "BufferMapAsync");
+                    return;
+                }
+                match host_map {
+                    HostMap::Read => {
+                        let map_sender = sender.clone();
+                        let on_read = move |status: wgpu::resource::BufferMapAsyncStatus,
+                                            ptr: *const u8| {
+                            let response = match status {
+                                wgpu::resource::BufferMapAsyncStatus::Success => {
+                                    let data =
+                                        unsafe { std::slice::from_raw_parts(ptr, size as usize) };
+                                    Ok(WebGPUResponse::BufferMapAsync {
+                                        data: data.to_vec(),
+                                    })
+                                },
+                                _ => Err("BufferMapAsync: Failed to map buffer".to_owned()),
+                            };
+                            if let Err(e) = map_sender.send(response) {
+                                warn!(
+                                    "Failed to send response to WebGPURequest::BufferMapAsync ({})",
+                                    e
+                                )
+                            }
+                        };
+                        let result = self.call_wgpu(Some(device_id), |global| {
+                            gfx_select!(buffer_id => global.buffer_map_async(
+                                buffer_id,
+                                wgpu::resource::BufferUsage::MAP_READ,
+                                offset..offset + size,
+                                wgpu::resource::BufferMapOperation::Read(Box::new(on_read))
+                            ));
+                            gfx_select!(device_id => global.device_poll(device_id, true));
+                            // Script gets a copy of the contents, so the buffer can be
+                            // unmapped right away.
+                            gfx_select!(buffer_id => global.buffer_unmap(buffer_id));
+                        });
+                        if let Err(e) = result {
+                            self.respond(
+                                &sender,
+                                Err(format!("BufferMapAsync: {:?}", e)),
+                                "BufferMapAsync",
+                            );
+                        }
+                    },
+                    HostMap::Write => {
+                        // The contents are written with `device_set_buffer_sub_data` on unmap,
+                        // so script starts from a zeroed mapping and nothing is mapped here.
+                        self.write_mappings.insert(buffer_id, offset..offset + size);
+                        self.respond(
+                            &sender,
+                            Ok(WebGPUResponse::BufferMapAsync { data: vec![] }),
+                            "BufferMapAsync",
+                        );
+                    },
+                }
+            },
+            WebGPURequest::CommandEncoderFinish {
+                sender,
+                command_encoder_id,
+            } => {
+                let device_id = self.command_encoder_devices.remove(&command_encoder_id);
+                let result = self.call_wgpu(device_id, |global| {
                     let command_buffer_id = gfx_select!(command_encoder_id => global.command_encoder_finish(
                         command_encoder_id,
                         &wgpu::command::CommandBufferDescriptor::default()
                     ));
-                    if let Err(e) = sender.send(WebGPUCommandBuffer(command_buffer_id)) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CommandEncoderFinish ({})",
-                            e
-                        )
+                    WebGPUCommandBuffer(command_buffer_id)
+                });
+                let result = result.and_then(|command_buffer| {
+                    match self.invalid_command_buffers.get(&command_encoder_id) {
+                        Some(e) => Err(WebGPUError::Validation(e.clone())),
+                        None => Ok(command_buffer),
                     }
-                },
-                WebGPURequest::CopyBufferToBuffer {
-                    command_encoder_id,
-                    source_id,
-                    source_offset,
-                    destination_id,
-                    destination_offset,
-                    size,
-                } => {
-                    let global = &self.global;
-                    let _ = gfx_select!(command_encoder_id => global.command_encoder_copy_buffer_to_buffer(
+                });
+                self.respond(&sender, result, "CommandEncoderFinish");
+            },
+            WebGPURequest::CopyBufferToBuffer {
+                command_encoder_id,
+                source_id,
+                source_offset,
+                destination_id,
+                destination_offset,
+                size,
+            } => {
+                let result = self
+                    .check_not_destroyed(source_id)
+                    .and_then(|()| self.check_not_destroyed(destination_id));
+                if let Err(e) = result {
+                    // Finishing or submitting the encoder reports the error.
+                    warn!("Rejecting WebGPURequest::CopyBufferToBuffer: {}", e);
+                    self.invalid_command_buffers
+                        .entry(command_encoder_id)
+                        .or_insert(e);
+                    return;
+                }
+                let device_id = self
+                    .command_encoder_devices
+                    .get(&command_encoder_id)
+                    .cloned();
+                let result = self.call_wgpu(device_id, |global| {
+                    gfx_select!(command_encoder_id => global.command_encoder_copy_buffer_to_buffer(
                         command_encoder_id,
                         source_id,
                         source_offset,
                         destination_id,
                         destination_offset,
                         size
-                    ));
-                },
-                WebGPURequest::CreateBindGroup {
-                    sender,
-                    device_id,
-                    bind_group_id,
-                    bind_group_layout_id,
-                    bindings,
-                } => {
-                    let global = &self.global;
-                    let descriptor = wgpu_core::binding_model::BindGroupDescriptor {
-                        layout: bind_group_layout_id,
-                        bindings: bindings.as_ptr(),
-                        bindings_length: bindings.len(),
-                    };
-                    let bg_id = gfx_select!(bind_group_id =>
-                        global.device_create_bind_group(device_id, &descriptor, bind_group_id));
-                    let bind_group = WebGPUBindGroup(bg_id);
-
-                    if let Err(e) = sender.send(bind_group) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateBindGroup ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::CreateBindGroupLayout {
-                    sender,
-                    device_id,
-                    bind_group_layout_id,
-                    bindings,
-                } => {
-                    let global = &self.global;
-                    let descriptor = wgpu_core::binding_model::BindGroupLayoutDescriptor {
-                        bindings: bindings.as_ptr(),
-                        bindings_length: bindings.len(),
-                    };
-                    let bgl_id = gfx_select!(bind_group_layout_id =>
-                        global.device_create_bind_group_layout(device_id, &descriptor, bind_group_layout_id));
-                    let bgl = WebGPUBindGroupLayout(bgl_id);
-
-                    if let Err(e) = sender.send(bgl) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateBindGroupLayout ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::CreateBuffer {
-                    sender,
-                    device_id,
-                    buffer_id,
-                    descriptor,
-                } => {
-                    let global = &self.global;
-                    let id = gfx_select!(buffer_id => global.device_create_buffer(device_id, &descriptor, buffer_id));
-                    let buffer = WebGPUBuffer(id);
-                    if let Err(e) = sender.send(buffer) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateBuffer ({})",
-                            e
+                    ))
+                });
+                if let Err(e) = result {
+                    warn!("Failed to copy between buffers ({:?})", e);
+                }
+            },
+            WebGPURequest::CopyBufferToTexture {
+                sender,
+                command_encoder_id,
+                source,
+                destination,
+                copy_size,
+            } => {
+                let result = self
+                    .validate_buffer_texture_copy(
+                        &source,
+                        wgpu::resource::BufferUsage::COPY_SRC,
+                        &destination,
+                        &copy_size,
+                    )
+                    .and_then(|()| {
+                        let device_id =
+                            self.command_encoder_devices.get(&command_encoder_id).cloned();
+                        self.call_wgpu(device_id, |global| {
+                            gfx_select!(command_encoder_id => global.command_encoder_copy_buffer_to_texture(
+                                command_encoder_id,
+                                &source,
+                                &destination,
+                                copy_size
+                            ))
+                        })
+                    });
+                self.respond(&sender, result, "CopyBufferToTexture");
+            },
+            WebGPURequest::CopyTextureToBuffer {
+                sender,
+                command_encoder_id,
+                source,
+                destination,
+                copy_size,
+            } => {
+                let result = self
+                    .validate_buffer_texture_copy(
+                        &destination,
+                        wgpu::resource::BufferUsage::COPY_DST,
+                        &source,
+                        &copy_size,
+                    )
+                    .and_then(|()| {
+                        let device_id =
+                            self.command_encoder_devices.get(&command_encoder_id).cloned();
+                        self.call_wgpu(device_id, |global| {
+                            gfx_select!(command_encoder_id => global.command_encoder_copy_texture_to_buffer(
+                                command_encoder_id,
+                                &source,
+                                &destination,
+                                copy_size
+                            ))
+                        })
+                    });
+                self.respond(&sender, result, "CopyTextureToBuffer");
+            },
+            WebGPURequest::CreateBindGroup {
+                sender,
+                device_id,
+                bind_group_id,
+                bind_group_layout_id,
+                bindings,
+            } => {
+                let result = self
+                    .validate_bind_group_bindings(bind_group_layout_id, &bindings)
+                    .and_then(|()| self.check_device(device_id))
+                    .and_then(|()| {
+                        let descriptor = wgpu_core::binding_model::BindGroupDescriptor {
+                            layout: bind_group_layout_id,
+                            bindings: bindings.as_ptr(),
+                            bindings_length: bindings.len(),
+                        };
+                        self.create_validated(device_id, |global| {
+                            let bg_id = gfx_select!(bind_group_id =>
+                                global.device_create_bind_group(device_id, &descriptor, bind_group_id));
+                            WebGPUBindGroup(bg_id)
+                        })
+                    });
+                if result.is_ok() {
+                    let count = self
+                        .layout_dynamic_offset_counts
+                        .get(&bind_group_layout_id)
+                        .cloned()
+                        .unwrap_or(0);
+                    self.bind_group_dynamic_offset_counts
+                        .insert(bind_group_id, count);
+                }
+
+                self.respond(&sender, result, "CreateBindGroup");
+            },
+            WebGPURequest::CreateBindGroupLayout {
+                sender,
+                device_id,
+                bind_group_layout_id,
+                bindings,
+            } => {
+                let descriptor = wgpu_core::binding_model::BindGroupLayoutDescriptor {
+                    bindings: bindings.as_ptr(),
+                    bindings_length: bindings.len(),
+                };
+                let result = self.check_device(device_id).and_then(|()| {
+                    self.create_validated(device_id, |global| {
+                        let bgl_id = gfx_select!(bind_group_layout_id =>
+                            global.device_create_bind_group_layout(device_id, &descriptor, bind_group_layout_id));
+                        WebGPUBindGroupLayout(bgl_id)
+                    })
+                });
+                if result.is_ok() {
+                    let types = bindings
+                        .iter()
+                        .map(|binding| (binding.binding, binding.ty))
+                        .collect();
+                    self.bind_group_layout_types
+                        .insert(bind_group_layout_id, types);
+                    let dynamic_count = bindings.iter().filter(|binding| binding.dynamic).count();
+                    self.layout_dynamic_offset_counts
+                        .insert(bind_group_layout_id, dynamic_count);
+                }
+
+                self.respond(&sender, result, "CreateBindGroupLayout");
+            },
+            WebGPURequest::CreateBuffer {
+                sender,
+                device_id,
+                buffer_id,
+                descriptor,
+            } => {
+                let result = validate_buffer_usage(descriptor.usage)
+                    .map_err(WebGPUError::Validation)
+                    .and_then(|()| {
+                        self.create_buffer_validated(device_id, |global| {
+                            let id = gfx_select!(buffer_id => global.device_create_buffer(device_id, &descriptor, buffer_id));
+                            WebGPUBuffer(id)
+                        })
+                    });
+                if result.is_ok() {
+                    self.destroyed_buffers.remove(&buffer_id);
+                    self.buffer_devices.insert(buffer_id, device_id);
+                    self.buffer_sizes.insert(buffer_id, descriptor.size);
+                    self.buffer_usages.insert(buffer_id, descriptor.usage);
+                }
+                self.respond(&sender, result, "CreateBuffer");
+            },
+            WebGPURequest::CreateBufferInit {
+                sender,
+                device_id,
+                buffer_id,
+                descriptor,
+                data,
+            } => {
+                let result = if data.len() as BufferAddress > descriptor.size {
+                    Err(WebGPUError::Validation(format!(
+                        "{} bytes of data do not fit in a buffer of {} bytes",
+                        data.len(),
+                        descriptor.size
+                    )))
+                } else {
+                    validate_buffer_usage(descriptor.usage).map_err(WebGPUError::Validation)
+                }
+                .and_then(|()| {
+                    self.create_buffer_validated(device_id, |global| {
+                        let (buffer_id, mapping) = gfx_select!(buffer_id =>
+                            global.device_create_buffer_mapped(device_id, &descriptor, buffer_id));
+                        // Copy straight from the shared memory into the mapping, and zero
+                        // the rest, which wgpu-core does not initialize. An empty buffer
+                        // may have no mapping at all.
+                        if descriptor.size > 0 && !mapping.is_null() {
+                            unsafe {
+                                std::ptr::copy_nonoverlapping(data.as_ptr(), mapping, data.len());
+                                std::ptr::write_bytes(
+                                    mapping.add(data.len()),
+                                    0,
+                                    descriptor.size as usize - data.len(),
+                                );
+                            }
+                        }
+                        gfx_select!(buffer_id => global.buffer_unmap(buffer_id));
+                        WebGPUBuffer(buffer_id)
+                    })
+                });
+                if result.is_ok() {
+                    self.destroyed_buffers.remove(&buffer_id);
+                    self.buffer_devices.insert(buffer_id, device_id);
+                    self.buffer_sizes.insert(buffer_id, descriptor.size);
+                    self.buffer_usages.insert(buffer_id, descriptor.usage);
+                }
+                self.respond(&sender, result, "CreateBufferInit");
+            },
+            WebGPURequest::CreateBufferMapped {
+                sender,
+                device_id,
+                buffer_id,
+                descriptor,
+            } => {
+                let result = validate_buffer_usage(descriptor.usage)
+                    .map_err(WebGPUError::Validation)
+                    .and_then(|()| {
+                        self.create_buffer_validated(device_id, |global| {
+                            let (buffer_id, _arr_buff_ptr) = gfx_select!(buffer_id =>
+                                global.device_create_buffer_mapped(device_id, &descriptor, buffer_id));
+                            WebGPUBuffer(buffer_id)
+                        })
+                    });
+                if result.is_ok() {
+                    self.destroyed_buffers.remove(&buffer_id);
+                    self.buffer_devices.insert(buffer_id, device_id);
+                    self.buffer_sizes.insert(buffer_id, descriptor.size);
+                    self.buffer_usages.insert(buffer_id, descriptor.usage);
+                }
+
+                self.respond(&sender, result, "CreateBufferMapped");
+            },
+            WebGPURequest::CreateCommandEncoder {
+                sender,
+                device_id,
+                command_encoder_id,
+            } => {
+                let result = match self.torn_down_devices.get(&device_id) {
+                    Some(error) => Err(error.clone()),
+                    None => self.call_wgpu(Some(device_id), |global| {
+                        let id = gfx_select!(command_encoder_id =>
+                            global.device_create_command_encoder(device_id, &Default::default(), command_encoder_id));
+                        WebGPUCommandEncoder(id)
+                    }),
+                };
+                if result.is_ok() {
+                    // The encoder id is reused for the command buffer it finishes into,
+                    // so a new encoder makes the id submittable again.
+                    self.submitted_command_buffers.remove(&command_encoder_id);
+                    self.invalid_command_buffers.remove(&command_encoder_id);
+                    self.command_encoder_devices
+                        .insert(command_encoder_id, device_id);
+                }
+                self.respond(&sender, result, "CreateCommandEncoder");
+            },
+            WebGPURequest::CreateComputePipeline {
+                sender,
+                device_id,
+                compute_pipeline_id,
+                pipeline_layout_id,
+                program_id,
+                entry_point,
+            } => {
+                let result = self
+                    .validate_entry_point(
+                        program_id,
+                        SPIRV_EXECUTION_MODEL_GL_COMPUTE,
+                        &entry_point,
+                    )
+                    .and_then(|()| self.check_device(device_id))
+                    .and_then(|()| {
+                        self.create_compute_pipeline(
+                            device_id,
+                            compute_pipeline_id,
+                            pipeline_layout_id,
+                            program_id,
+                            entry_point,
                         )
+                    });
+
+                self.respond(&sender, result, "CreateComputePipeline");
+            },
+            WebGPURequest::CreatePipelineLayout {
+                sender,
+                device_id,
+                pipeline_layout_id,
+                bind_group_layouts,
+            } => {
+                let descriptor = wgpu_core::binding_model::PipelineLayoutDescriptor {
+                    bind_group_layouts: bind_group_layouts.as_ptr(),
+                    bind_group_layouts_length: bind_group_layouts.len(),
+                };
+                let result = self.check_device(device_id).and_then(|()| {
+                    self.create_validated(device_id, |global| {
+                        let pl_id = gfx_select!(pipeline_layout_id =>
+                            global.device_create_pipeline_layout(device_id, &descriptor, pipeline_layout_id));
+                        WebGPUPipelineLayout(pl_id)
+                    })
+                });
+                if result.is_ok() {
+                    self.bind_group_layout_counts
+                        .insert(pipeline_layout_id, bind_group_layouts.len() as u32);
+                }
+
+                self.respond(&sender, result, "CreatePipelineLayout");
+            },
+            WebGPURequest::CreateSampler {
+                sender,
+                device_id,
+                sampler_id,
+                descriptor,
+            } => {
+                let result = self.check_device(device_id).and_then(|()| {
+                    self.create_validated(device_id, |global| {
+                        let id = gfx_select!(sampler_id =>
+                            global.device_create_sampler(device_id, &descriptor, sampler_id));
+                        WebGPUSampler(id)
+                    })
+                });
+                if result.is_ok() {
+                    // Samplers that always pass the comparison are plain samplers.
+                    let is_comparison =
+                        descriptor.compare_function != wgpu::resource::CompareFunction::Always;
+                    self.comparison_samplers.insert(sampler_id, is_comparison);
+                }
+                self.respond(&sender, result, "CreateSampler");
+            },
+            WebGPURequest::CreateShaderModule {
+                sender,
+                device_id,
+                program_id,
+                program,
+            } => {
+                let descriptor = wgpu_core::pipeline::ShaderModuleDescriptor {
+                    code: wgpu_core::U32Array {
+                        bytes: program.as_ptr(),
+                        length: program.len(),
+                    },
+                };
+                let result = validate_spirv_header(&program)
+                    .map_err(WebGPUError::Validation)
+                    .and_then(|()| self.check_device(device_id))
+                    .and_then(|()| {
+                        self.create_validated(device_id, |global| {
+                            let sm_id = gfx_select!(program_id =>
+                                global.device_create_shader_module(device_id, &descriptor, program_id));
+                            WebGPUShaderModule(sm_id)
+                        })
+                    });
+                if result.is_ok() {
+                    self.shader_entry_points
+                        .insert(program_id, spirv_entry_points(&program));
+                }
+
+                self.respond(&sender, result, "CreateShaderModule");
+            },
+            WebGPURequest::CreateTexture {
+                sender,
+                device_id,
+                texture_id,
+                descriptor,
+            } => {
+                // No texture format of this wgpu-core depends on a device extension,
+                // so whatever it rejects is reported as is.
+                let result = self
+                    .check_device(device_id)
+                    .and_then(|()| {
+                        validate_texture_size(&descriptor.size).map_err(|e| {
+                            let error = WebGPUError::Validation(e);
+                            self.report_error(device_id, error.clone());
+                            error
+                        })
+                    })
+                    .and_then(|()| {
+                        self.create_validated(device_id, |global| {
+                            let id = gfx_select!(texture_id =>
+                                global.device_create_texture(device_id, &descriptor, texture_id));
+                            WebGPUTexture(id)
+                        })
+                    });
+                if result.is_ok() {
+                    self.texture_formats.insert(texture_id, descriptor.format);
+                }
+
+                self.respond(&sender, result, "CreateTexture");
+            },
+            WebGPURequest::CreateTextureView {
+                sender,
+                device_id,
+                texture_id,
+                texture_view_id,
+                descriptor,
+            } => {
+                // This wgpu-core cannot reinterpret the texels of a texture,
+                // so a view can only have the format of its texture.
+                let result = match (self.texture_formats.get(&texture_id), &descriptor) {
+                    (None, _) => Err(WebGPUError::Validation("invalid texture".to_owned())),
+                    (Some(&format), Some(descriptor)) if descriptor.format != format => {
+                        Err(WebGPUError::Validation(format!(
+                            "the view format {:?} is not the format {:?} of the texture",
+                            descriptor.format, format
+                        )))
+                    },
+                    _ => self.check_device(device_id),
+                }
+                .and_then(|()| {
+                    self.create_validated(device_id, |global| {
+                        let id = gfx_select!(texture_view_id =>
+                            global.texture_create_view(texture_id, descriptor.as_ref(), texture_view_id));
+                        WebGPUTextureView(id)
+                    })
+                });
+                if result.is_ok() {
+                    self.texture_view_devices.insert(texture_view_id, device_id);
+                }
+
+                self.respond(&sender, result, "CreateTextureView");
+            },
+            WebGPURequest::DestroyBuffer(buffer) => {
+                // Buffers that failed to be created are unknown to wgpu-core.
+                self.write_mappings.remove(&buffer);
+                self.buffer_sizes.remove(&buffer);
+                self.buffer_usages.remove(&buffer);
+                if let Some(device_id) = self.buffer_devices.remove(&buffer) {
+                    self.destroyed_buffers.insert(buffer);
+                    self.release_buffer(device_id);
+                    let result = self.call_wgpu(
+                        Some(device_id),
+                        |global| gfx_select!(buffer => global.buffer_destroy(buffer)),
+                    );
+                    if let Err(e) = result {
+                        warn!("Failed to destroy buffer {:?} ({:?})", buffer, e);
                     }
-                },
-                WebGPURequest::CreateBufferMapped {
-                    sender,
-                    device_id,
-                    buffer_id,
-                    descriptor,
-                } => {
-                    let global = &self.global;
-                    let (buffer_id, _arr_buff_ptr) = gfx_select!(buffer_id =>
-                        global.device_create_buffer_mapped(device_id, &descriptor, buffer_id));
-                    let buffer = WebGPUBuffer(buffer_id);
-
-                    if let Err(e) = sender.send(buffer) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateBufferMapped ({})",
-                            e
-                        )
+                }
+            },
+            WebGPURequest::DestroyDevice { device_id } => {
+                if !self.devices.iter().any(|device| device.0 == device_id) {
+                    warn!("Rejecting WebGPURequest::DestroyDevice: unknown or lost device");
+                    return;
+                }
+                let idle_requests = std::mem::replace(&mut self.idle_requests, VecDeque::new());
+                for request in idle_requests {
+                    if request.device_id == device_id {
+                        self.respond(
+                            &request.sender,
+                            Err(WebGPUError::Destroyed),
+                            "PrecompileComputePipeline",
+                        );
+                    } else {
+                        self.idle_requests.push_back(request);
                     }
-                },
-                WebGPURequest::CreateCommandEncoder {
-                    sender,
-                    device_id,
-                    command_encoder_id,
-                } => {
-                    let global = &self.global;
-                    let id = gfx_select!(command_encoder_id =>
-                        global.device_create_command_encoder(device_id, &Default::default(), command_encoder_id));
-                    if let Err(e) = sender.send(WebGPUCommandEncoder(id)) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateCommandEncoder ({})",
-                            e
-                        )
+                }
+                let result = self.call_wgpu(
+                    Some(device_id),
+                    |global| gfx_select!(device_id => global.device_poll(device_id, true)),
+                );
+                // Torn down with `WebGPUError::Internal` already otherwise.
+                if result.is_ok() {
+                    self.tear_down_device(device_id, WebGPUError::Destroyed);
+                }
+            },
+            WebGPURequest::DevicePoll {
+                device_id,
+                force_wait,
+            } => {
+                if self.devices.iter().any(|device| device.0 == device_id) {
+                    let _ = self.call_wgpu(Some(device_id), |global| {
+                        gfx_select!(device_id => global.device_poll(device_id, force_wait))
+                    });
+                }
+            },
+            WebGPURequest::DropBuffer(buffer) => {
+                self.destroyed_buffers.remove(&buffer);
+            },
+            WebGPURequest::DropTextureView(texture_view) => {
+                // Views that failed to be created are unknown to wgpu-core.
+                if let Some(device_id) = self.texture_view_devices.remove(&texture_view) {
+                    let result = self.call_wgpu(Some(device_id), |global| {
+                        gfx_select!(texture_view => global.texture_view_destroy(texture_view))
+                    });
+                    if let Err(e) = result {
+                        warn!("Failed to drop texture view {:?} ({:?})", texture_view, e);
                     }
-                },
-                WebGPURequest::CreateComputePipeline {
+                }
+            },
+            // Handled by `run`, since it consumes `self`.
+            WebGPURequest::Exit(_) => unreachable!(),
+            WebGPURequest::GetDeviceCapabilities { sender, device_id } => {
+                let result = if let Some(error) = self.torn_down_devices.get(&device_id) {
+                    Err(error.clone())
+                } else if !self.devices.iter().any(|device| device.0 == device_id) {
+                    Err(WebGPUError::Validation("invalid device".to_owned()))
+                } else {
+                    self.call_wgpu(Some(device_id), |global| {
+                        let limits = gfx_select!(device_id => global.device_limits(device_id));
+                        WebGPUDeviceCapabilities {
+                            max_bind_groups: limits.max_bind_groups,
+                            max_vertex_attributes: MAX_VERTEX_ATTRIBUTES,
+                            max_vertex_buffers: wgpu::device::MAX_VERTEX_BUFFERS as u32,
+                            max_color_attachments: wgpu::device::MAX_COLOR_TARGETS as u32,
+                        }
+                    })
+                };
+                self.respond(&sender, result, "GetDeviceCapabilities");
+            },
+            WebGPURequest::GetPipelineBindGroupLayoutCount {
+                sender,
+                pipeline_id,
+            } => {
+                let count = self
+                    .compute_pipeline_layouts
+                    .get(&pipeline_id)
+                    .and_then(|layout_id| self.bind_group_layout_counts.get(layout_id))
+                    .cloned()
+                    .unwrap_or(0);
+                self.respond(&sender, count, "GetPipelineBindGroupLayoutCount");
+            },
+            WebGPURequest::HealthCheck(sender) => {
+                let orphaned_requests = self.orphaned_requests.drain(..).collect::<Vec<_>>();
+                self.respond(&sender, orphaned_requests, "HealthCheck");
+            },
+            WebGPURequest::PrecompileComputePipeline {
+                sender,
+                device_id,
+                compute_pipeline_id,
+                pipeline_layout_id,
+                program_id,
+                entry_point,
+            } => {
+                self.idle_requests.push_back(PrecompileComputePipeline {
                     sender,
                     device_id,
                     compute_pipeline_id,
                     pipeline_layout_id,
                     program_id,
                     entry_point,
-                } => {
-                    let global = &self.global;
-                    let entry_point = std::ffi::CString::new(entry_point).unwrap();
-                    let descriptor = wgpu_core::pipeline::ComputePipelineDescriptor {
-                        layout: pipeline_layout_id,
-                        compute_stage: wgpu_core::pipeline::ProgrammableStageDescriptor {
-                            module: program_id,
-                            entry_point: entry_point.as_ptr(),
-                        },
-                    };
-                    let cp_id = gfx_select!(compute_pipeline_id =>
-                        global.device_create_compute_pipeline(device_id, &descriptor, compute_pipeline_id));
-                    let compute_pipeline = WebGPUComputePipeline(cp_id);
-
-                    if let Err(e) = sender.send(compute_pipeline) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateComputePipeline ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::CreatePipelineLayout {
-                    sender,
-                    device_id,
-                    pipeline_layout_id,
-                    bind_group_layouts,
-                } => {
-                    let global = &self.global;
-                    let descriptor = wgpu_core::binding_model::PipelineLayoutDescriptor {
-                        bind_group_layouts: bind_group_layouts.as_ptr(),
-                        bind_group_layouts_length: bind_group_layouts.len(),
-                    };
-                    let pl_id = gfx_select!(pipeline_layout_id =>
-                        global.device_create_pipeline_layout(device_id, &descriptor, pipeline_layout_id));
-                    let pipeline_layout = WebGPUPipelineLayout(pl_id);
-
-                    if let Err(e) = sender.send(pipeline_layout) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreatePipelineLayout ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::CreateShaderModule {
-                    sender,
-                    device_id,
-                    program_id,
-                    program,
-                } => {
-                    let global = &self.global;
-                    let descriptor = wgpu_core::pipeline::ShaderModuleDescriptor {
-                        code: wgpu_core::U32Array {
-                            bytes: program.as_ptr(),
-                            length: program.len(),
+                });
+            },
+            WebGPURequest::RequestAdapter {
+                sender,
+                options,
+                force_fallback_adapter,
+                ids,
+            } => {
+                let adapter_id = if force_fallback_adapter {
+                    let known = self.adapters.clone();
+                    let fallback = self.call_wgpu(None, |global| {
+                        Self::pick_fallback_adapter(global, &known, &options, &ids)
+                    });
+                    match fallback {
+                        Ok(Some(id)) => id,
+                        _ => {
+                            self.respond(
+                                &sender,
+                                Err("No fallback adapter available".to_owned()),
+                                "RequestAdapter",
+                            );
+                            return;
                         },
-                    };
-                    let sm_id = gfx_select!(program_id =>
-                        global.device_create_shader_module(device_id, &descriptor, program_id));
-                    let shader_module = WebGPUShaderModule(sm_id);
-
-                    if let Err(e) = sender.send(shader_module) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::CreateShaderModule ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::DestroyBuffer(buffer) => {
-                    let global = &self.global;
-                    gfx_select!(buffer => global.buffer_destroy(buffer));
-                },
-                WebGPURequest::Exit(sender) => {
-                    self.deinit();
-                    if let Err(e) = sender.send(()) {
-                        warn!("Failed to send response to WebGPURequest::Exit ({})", e)
                     }
-                    return;
-                },
-                WebGPURequest::MapReadAsync {
-                    sender,
-                    buffer_id,
-                    device_id,
-                    usage,
-                    size,
-                } => {
-                    let global = &self.global;
-                    let on_read = move |status: wgpu::resource::BufferMapAsyncStatus,
-                                        ptr: *const u8| {
-                        match status {
-                            wgpu::resource::BufferMapAsyncStatus::Success => {
-                                let array_buffer =
-                                    unsafe { std::slice::from_raw_parts(ptr, size as usize) };
-                                if let Err(e) = sender.send(Ok(WebGPUResponse::MapReadAsync(
-                                    IpcSharedMemory::from_bytes(array_buffer),
-                                ))) {
-                                    warn!(
-                                        "Failed to send response to WebGPURequest::MapReadAsync ({})",
-                                        e
-                                    )
-                                }
-                            },
-                            _ => {
-                                if let Err(e) = sender
-                                    .send(Err("MapReadAsync: Failed to map buffer".to_owned()))
-                                {
-                                    warn!(
-                                        "Failed to send response to WebGPURequest::MapReadAsync ({})",
-                                        e
-                                    )
-                                }
-                            },
-                        }
-                    };
-                    gfx_select!(buffer_id => global.buffer_map_async(
-                        buffer_id,
-                        wgpu::resource::BufferUsage::from_bits(usage).unwrap(),
-                        0..size,
-                        wgpu::resource::BufferMapOperation::Read(Box::new(on_read))
-                    ));
-                    gfx_select!(device_id => global.device_poll(device_id, true));
-                },
-                WebGPURequest::RequestAdapter {
-                    sender,
-                    options,
-                    ids,
-                } => {
-                    let adapter_id = if let Some(pos) = self
-                        .adapters
-                        .iter()
-                        .position(|adapter| ids.contains(&adapter.0))
-                    {
-                        self.adapters[pos].0
-                    } else {
-                        let adapter_id = match self.global.pick_adapter(
+                } else if let Some(pos) = self
+                    .adapters
+                    .iter()
+                    .position(|adapter| ids.contains(&adapter.0))
+                {
+                    self.adapters[pos].0
+                } else {
+                    let picked = self.call_wgpu(None, |global| {
+                        global.pick_adapter(
                             &options,
                             wgpu::instance::AdapterInputs::IdSet(&ids, |id| id.backend()),
-                        ) {
-                            Some(id) => id,
-                            None => {
-                                if let Err(e) =
-                                    sender.send(Err("Failed to get webgpu adapter".to_string()))
-                                {
-                                    warn!(
-                                        "Failed to send response to WebGPURequest::RequestAdapter ({})",
-                                        e
-                                    )
-                                }
-                                return;
-                            },
-                        };
-                        adapter_id
+                        )
+                    });
+                    let adapter_id = match picked {
+                        Ok(Some(id)) => id,
+                        _ => {
+                            self.respond(
+                                &sender,
+                                Err("Failed to get webgpu adapter".to_string()),
+                                "RequestAdapter",
+                            );
+                            return;
+                        },
                     };
-                    let adapter = WebGPUAdapter(adapter_id);
+                    adapter_id
+                };
+                let adapter = WebGPUAdapter(adapter_id);
+                if !self.adapters.contains(&adapter) {
                     self.adapters.push(adapter);
-                    let global = &self.global;
+                }
+                let channel = WebGPU(self.sender.clone());
+                let response = self.call_wgpu(None, |global| {
                     let info = gfx_select!(adapter_id => global.adapter_get_info(adapter_id));
-                    if let Err(e) = sender.send(Ok(WebGPUResponse::RequestAdapter {
+                    WebGPUResponse::RequestAdapter {
                         adapter_name: info.name,
                         adapter_id: adapter,
-                        channel: WebGPU(self.sender.clone()),
-                    })) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::RequestAdapter ({})",
-                            e
-                        )
+                        vendor_id: info.vendor as u32,
+                        device_id: info.device as u32,
+                        extensions: gfx_select!(adapter_id => global.adapter_extensions(adapter_id)),
+                        limits: gfx_select!(adapter_id => global.adapter_limits(adapter_id)),
+                        // Software implementations are what the spec calls fallback adapters.
+                        is_fallback: info.device_type == wgpu::instance::DeviceType::Cpu,
+                        channel,
                     }
-                },
-                WebGPURequest::RequestDevice {
-                    sender,
-                    adapter_id,
-                    descriptor,
-                    device_id,
-                } => {
-                    let global = &self.global;
-                    let id = gfx_select!(device_id => global.adapter_request_device(
+                });
+                self.respond(
+                    &sender,
+                    response.map_err(|e| format!("RequestAdapter: {:?}", e)),
+                    "RequestAdapter",
+                );
+            },
+            WebGPURequest::RequestDevice {
+                sender,
+                adapter_id,
+                descriptor,
+                device_id,
+                lost_sender,
+            } => {
+                // https://gpuweb.github.io/gpuweb/#dom-gpuadapter-requestdevice
+                let adapter_support = self.call_wgpu(None, |global| {
+                    (
+                        gfx_select!(adapter_id.0 => global.adapter_extensions(adapter_id.0)),
+                        gfx_select!(adapter_id.0 => global.adapter_limits(adapter_id.0)),
+                    )
+                });
+                let (supported, limits) = match adapter_support {
+                    Ok(support) => support,
+                    Err(e) => {
+                        self.respond(
+                            &sender,
+                            Err(format!("RequestDevice: {:?}", e)),
+                            "RequestDevice",
+                        );
+                        return;
+                    },
+                };
+                let mut unsupported = vec![];
+                if descriptor.extensions.anisotropic_filtering && !supported.anisotropic_filtering {
+                    unsupported.push("anisotropicFiltering");
+                }
+                if !unsupported.is_empty() {
+                    self.respond(
+                        &sender,
+                        Err(format!("Unsupported features: {}", unsupported.join(", "))),
+                        "RequestDevice",
+                    );
+                    return;
+                }
+                if descriptor.limits.max_bind_groups > limits.max_bind_groups {
+                    self.respond(
+                        &sender,
+                        Err(format!(
+                            "Unsupported limits: maxBindGroups {} exceeds {}",
+                            descriptor.limits.max_bind_groups, limits.max_bind_groups
+                        )),
+                        "RequestDevice",
+                    );
+                    return;
+                }
+                let requested = self.call_wgpu(None, |global| {
+                    gfx_select!(device_id => global.adapter_request_device(
                         adapter_id.0,
                         &descriptor,
                         device_id
-                    ));
+                    ))
+                });
+                let id = match requested {
+                    Ok(id) => id,
+                    Err(e) => {
+                        self.respond(
+                            &sender,
+                            Err(format!("RequestDevice: {:?}", e)),
+                            "RequestDevice",
+                        );
+                        return;
+                    },
+                };
 
-                    let device = WebGPUDevice(id);
-                    // Note: (zakorgy) Note sure if sending the queue is needed at all,
-                    // since wgpu-core uses the same id for the device and the queue
-                    let queue = WebGPUQueue(id);
-                    self.devices.push(device);
-                    if let Err(e) = sender.send(Ok(WebGPUResponse::RequestDevice {
+                let device = WebGPUDevice(id);
+                // Note: (zakorgy) Note sure if sending the queue is needed at all,
+                // since wgpu-core uses the same id for the device and the queue
+                let queue = WebGPUQueue(id);
+                self.devices.push(device);
+                if let Some(lost_sender) = lost_sender {
+                    self.device_lost_senders.insert(id, lost_sender);
+                }
+                let delivered = self.respond(
+                    &sender,
+                    Ok(WebGPUResponse::RequestDevice {
                         device_id: device,
                         queue_id: queue,
                         _descriptor: descriptor,
-                    })) {
-                        warn!(
-                            "Failed to send response to WebGPURequest::RequestDevice ({})",
-                            e
-                        )
-                    }
-                },
-                WebGPURequest::RunComputePass {
-                    command_encoder_id,
-                    pass_data,
-                } => {
-                    let global = &self.global;
+                    }),
+                    "RequestDevice",
+                );
+                if !delivered {
+                    // Nobody will ever use or release this device.
+                    self.tear_down_device(id, WebGPUError::DeviceLost);
+                }
+            },
+            WebGPURequest::RunComputePass {
+                command_encoder_id,
+                pass_data,
+                bind_group_offsets,
+                indirect_buffers,
+            } => {
+                if let Err(e) = self.validate_compute_pass(&bind_group_offsets, &indirect_buffers) {
+                    // Finishing or submitting the encoder reports the error.
+                    warn!("Rejecting WebGPURequest::RunComputePass: {}", e);
+                    self.invalid_command_buffers
+                        .entry(command_encoder_id)
+                        .or_insert(e);
+                    return;
+                }
+                let device_id = self
+                    .command_encoder_devices
+                    .get(&command_encoder_id)
+                    .cloned();
+                let result = self.call_wgpu(device_id, |global| {
                     gfx_select!(command_encoder_id => global.command_encoder_run_compute_pass(
                         command_encoder_id,
                         &pass_data
-                    ));
-                },
-                WebGPURequest::Submit {
-                    queue_id,
-                    command_buffers,
-                } => {
-                    let global = &self.global;
-                    let _ = gfx_select!(queue_id => global.queue_submit(
+                    ))
+                });
+                if let Err(e) = result {
+                    warn!("Failed to run compute pass ({:?})", e);
+                }
+            },
+            WebGPURequest::Submit {
+                queue_id,
+                command_buffers,
+            } => {
+                let mut seen = HashSet::new();
+                if let Some(e) = command_buffers
+                    .iter()
+                    .find(|id| self.submitted_command_buffers.contains(id) || !seen.insert(**id))
+                    .map(|id| format!("command buffer {:?} was already submitted", id))
+                    .or_else(|| {
+                        command_buffers
+                            .iter()
+                            .find_map(|id| self.invalid_command_buffers.get(id).cloned())
+                    })
+                {
+                    warn!("Rejecting WebGPURequest::Submit: {}", e);
+                    return;
+                }
+                self.submitted_command_buffers
+                    .extend(command_buffers.iter().cloned());
+                // The queue id is the id of its device. Poll without waiting, to clean up
+                // after work that already finished.
+                let result = self.call_wgpu(Some(queue_id), |global| {
+                    gfx_select!(queue_id => global.queue_submit(
                         queue_id,
                         &command_buffers
                     ));
-                },
-                WebGPURequest::UnmapBuffer {
-                    device_id,
-                    buffer_id,
-                    array_buffer,
-                } => {
-                    let global = &self.global;
-
+                    gfx_select!(queue_id => global.device_poll(queue_id, false));
+                });
+                if let Err(e) = result {
+                    warn!("Failed to submit to queue {:?} ({:?})", queue_id, e);
+                }
+            },
+            WebGPURequest::UnmapBuffer {
+                device_id,
+                buffer_id,
+                array_buffer,
+            } => {
+                let (offset, data) = match self.write_mappings.remove(&buffer_id) {
+                    Some(range) => {
+                        let len = ((range.end - range.start) as usize).min(array_buffer.len());
+                        (range.start, &array_buffer[..len])
+                    },
+                    // Buffers created mapped are mapped in their entirety.
+                    None => (0, array_buffer.as_slice()),
+                };
+                // Unmapping a pending mapping writes nothing.
+                if data.is_empty() {
+                    return;
+                }
+                let result = self.call_wgpu(Some(device_id), |global| {
                     gfx_select!(buffer_id => global.device_set_buffer_sub_data(
                         device_id,
                         buffer_id,
-                        0,
-                        array_buffer.as_slice()
-                    ));
-                },
-            }
+                        offset,
+                        data
+                    ))
+                });
+                if let Err(e) = result {
+                    warn!("Failed to unmap buffer {:?} ({:?})", buffer_id, e);
+                }
+            },
+            WebGPURequest::WriteBuffer {
+                sender,
+                queue_id,
+                buffer_id,
+                buffer_offset,
+                data,
+            } => {
+                // The queue id is the id of its device.
+                let result = self
+                    .check_device(queue_id)
+                    .and_then(|()| {
+                        self.validate_buffer_write(
+                            buffer_id,
+                            buffer_offset,
+                            data.len() as BufferAddress,
+                        )
+                        .map_err(WebGPUError::Validation)
+                    })
+                    .and_then(|()| {
+                        self.call_wgpu(Some(queue_id), |global| {
+                            gfx_select!(buffer_id => global.device_set_buffer_sub_data(
+                                queue_id,
+                                buffer_id,
+                                buffer_offset,
+                                &data
+                            ))
+                        })
+                    });
+                self.respond(&sender, result, "WriteBuffer");
+            },
         }
     }
 }
 
+/// The message of a panic from wgpu-core, or `default` when it has none.
+fn panic_message(payload: Box<dyn Any + Send>, default: &str) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| default.to_owned())
+}
+
+/// Checks the size of a new texture, which wgpu-core accepts even when empty.
+/// https://gpuweb.github.io/gpuweb/#dom-gpudevice-createtexture
+fn validate_texture_size(size: &Extent3d) -> Result<(), String> {
+    if size.width == 0 || size.height == 0 || size.depth == 0 {
+        return Err("texture dimension must be non-zero".to_owned());
+    }
+    Ok(())
+}
+
+/// Checks the usage of a new buffer, so that a buffer that can never be mapped
+/// the way script expects is reported at creation rather than when mapping it.
+/// https://gpuweb.github.io/gpuweb/#dom-gpudevice-createbuffer
+fn validate_buffer_usage(usage: wgpu::resource::BufferUsage) -> Result<(), String> {
+    use wgpu::resource::BufferUsage;
+
+    if usage.is_empty() {
+        return Err("the buffer usage must not be empty".to_owned());
+    }
+    if usage.contains(BufferUsage::MAP_READ | BufferUsage::MAP_WRITE) {
+        return Err("MAP_READ and MAP_WRITE are mutually exclusive".to_owned());
+    }
+    if usage.contains(BufferUsage::MAP_READ) &&
+        !(BufferUsage::MAP_READ | BufferUsage::COPY_DST).contains(usage)
+    {
+        return Err(format!(
+            "MAP_READ can only be combined with COPY_DST, not {:?}",
+            usage - BufferUsage::MAP_READ - BufferUsage::COPY_DST
+        ));
+    }
+    if usage.contains(BufferUsage::MAP_WRITE) &&
+        !(BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC).contains(usage)
+    {
+        return Err(format!(
+            "MAP_WRITE can only be combined with COPY_SRC, not {:?}",
+            usage - BufferUsage::MAP_WRITE - BufferUsage::COPY_SRC
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects programs that are not SPIR-V at all, which wgpu-core would only
+/// report with a backend-specific failure.
+fn validate_spirv_header(program: &[u32]) -> Result<(), String> {
+    match program.first() {
+        None => Err("the shader module is empty".to_owned()),
+        Some(&SPIRV_MAGIC_NUMBER) if program.len() >= 5 => Ok(()),
+        Some(&SPIRV_MAGIC_NUMBER) => Err(format!(
+            "the SPIR-V header needs 5 words, but the module only has {}",
+            program.len()
+        )),
+        Some(word) => Err(format!(
+            "the shader module is not SPIR-V (magic number {:#010x} instead of {:#010x})",
+            word, SPIRV_MAGIC_NUMBER
+        )),
+    }
+}
+
+/// The execution model and name of every `OpEntryPoint` of a SPIR-V module.
+/// Malformed modules yield the entry points found before the error,
+/// wgpu-core rejects them anyway.
+fn spirv_entry_points(program: &[u32]) -> Vec<(u32, String)> {
+    let mut entry_points = Vec::new();
+    // Skip the header: magic number, version, generator, bound and schema.
+    let mut words = program.get(5..).unwrap_or(&[]);
+    while let Some(&first) = words.first() {
+        let word_count = (first >> 16) as usize;
+        if word_count == 0 || word_count > words.len() {
+            break;
+        }
+        let instruction = &words[..word_count];
+        words = &words[word_count..];
+        if first & 0xffff != SPIRV_OP_ENTRY_POINT || word_count < 4 {
+            continue;
+        }
+        // The name is a nul-terminated UTF-8 string, packed little-endian into words.
+        let bytes = instruction[3..]
+            .iter()
+            .flat_map(|word| word.to_le_bytes().to_vec())
+            .take_while(|&byte| byte != 0)
+            .collect::<Vec<u8>>();
+        if let Ok(name) = String::from_utf8(bytes) {
+            entry_points.push((instruction[1], name));
+        }
+    }
+    entry_points
+}
+
+/// The size of a texel block in bytes, for formats that can be copied.
+/// https://gpuweb.github.io/gpuweb/#texel-block-size
+fn texel_block_size(format: TextureFormat) -> Option<u32> {
+    use wgpu::resource::TextureFormat::*;
+    match format {
+        R8Unorm | R8Snorm | R8Uint | R8Sint => Some(1),
+        R16Uint | R16Sint | R16Float | Rg8Unorm | Rg8Snorm | Rg8Uint | Rg8Sint => Some(2),
+        R32Uint | R32Sint | R32Float | Rg16Uint | Rg16Sint | Rg16Float | Rgba8Unorm |
+        Rgba8UnormSrgb | Rgba8Snorm | Rgba8Uint | Rgba8Sint | Bgra8Unorm | Bgra8UnormSrgb |
+        Rgb10a2Unorm | Rg11b10Float | Depth32Float => Some(4),
+        Rg32Uint | Rg32Sint | Rg32Float | Rgba16Uint | Rgba16Sint | Rgba16Float => Some(8),
+        Rgba32Uint | Rgba32Sint | Rgba32Float => Some(16),
+        // The layout of depth24plus texels is up to the implementation.
+        Depth24Plus | Depth24PlusStencil8 => None,
+    }
+}
+
+/// The number of bytes a copy of `size` texels spans in linear texture data that starts
+/// at `offset`, after checking that the rows and images of the layout are large enough.
+/// https://gpuweb.github.io/gpuweb/#abstract-opdef-validating-linear-texture-data
+fn required_bytes_in_copy(
+    format: TextureFormat,
+    offset: BufferAddress,
+    bytes_per_row: u32,
+    rows_per_image: u32,
+    size: &Extent3d,
+) -> Result<BufferAddress, String> {
+    let block_size = match texel_block_size(format) {
+        Some(block_size) => block_size as BufferAddress,
+        None => return Err(format!("textures of format {:?} cannot be copied", format)),
+    };
+    let bytes_per_row = bytes_per_row as BufferAddress;
+    let bytes_in_last_row = size.width as BufferAddress * block_size;
+    if (size.height > 1 || size.depth > 1) && bytes_per_row < bytes_in_last_row {
+        return Err(format!(
+            "bytes per row ({}) must be at least the {} bytes of a row of the copy",
+            bytes_per_row, bytes_in_last_row
+        ));
+    }
+    let rows_per_image = match rows_per_image {
+        0 => size.height,
+        rows_per_image if rows_per_image < size.height => {
+            return Err(format!(
+                "rows per image ({}) must be at least the {} rows of the copy",
+                rows_per_image, size.height
+            ))
+        },
+        rows_per_image => rows_per_image,
+    } as BufferAddress;
+    if size.width == 0 || size.height == 0 || size.depth == 0 {
+        return Ok(offset);
+    }
+    let bytes_per_image = bytes_per_row * rows_per_image;
+    Ok(offset +
+        bytes_per_image * (size.depth as BufferAddress - 1) +
+        bytes_per_row * (size.height as BufferAddress - 1) +
+        bytes_in_last_row)
+}
+
 macro_rules! webgpu_resource {
     ($name:ident, $id:ty) => {
         #[derive(Clone, Copy, Debug, Deserialize, Hash, PartialEq, Serialize)]
@@ -606,4 +2200,27 @@ webgpu_resource!(WebGPUComputePipeline, ComputePipelineId);
 webgpu_resource!(WebGPUDevice, DeviceId);
 webgpu_resource!(WebGPUPipelineLayout, PipelineLayoutId);
 webgpu_resource!(WebGPUQueue, QueueId);
+webgpu_resource!(WebGPUSampler, SamplerId);
 webgpu_resource!(WebGPUShaderModule, ShaderModuleId);
+webgpu_resource!(WebGPUTexture, TextureId);
+webgpu_resource!(WebGPUTextureView, TextureViewId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_size_must_be_non_zero() {
+        let size = |width, height, depth| Extent3d {
+            width,
+            height,
+            depth,
+        };
+        assert!(validate_texture_size(&size(1, 1, 1)).is_ok());
+        assert!(validate_texture_size(&size(256, 128, 6)).is_ok());
+        assert!(validate_texture_size(&size(0, 1, 1)).is_err());
+        assert!(validate_texture_size(&size(1, 0, 1)).is_err());
+        assert!(validate_texture_size(&size(1, 1, 0)).is_err());
+        assert!(validate_texture_size(&size(0, 0, 0)).is_err());
+    }
+}
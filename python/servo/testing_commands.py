@@ -262,6 +262,7 @@ class MachCommands(CommandBase):
             "selectors",
             "servo_config",
             "servo_remutex",
+            "webgpu",
         ]
         if not packages:
             packages = set(os.listdir(path.join(self.context.topdir, "tests", "unit"))) - set(['.DS_Store'])